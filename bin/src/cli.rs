@@ -0,0 +1,400 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process;
+use types::eth::BlockTrace;
+use zkevm::{
+    circuit::{
+        check_batch_capacity_with_policy, check_gas_accounting, suggest_rechunk, BatchGasUsage,
+        BatchPolicy, CircuitCapacityExceeded, RowUsageReport, SuperCircuit, TargetCircuit,
+        AGG_DEGREE, DEGREE, SUB_CIRCUIT_NAMES,
+    },
+    io::vk_fingerprint,
+    prover::{AggCircuitProof, Prover, TargetCircuitProof},
+    utils::{
+        canonical_trace_hash, get_block_trace_from_file, load_or_create_params,
+        load_or_create_seed, read_env_var, sort_traces_by_block_number, NdjsonErrorPolicy,
+        TraceFormat, TraceSource,
+    },
+    verifier::Verifier,
+};
+
+const EXIT_ERROR: i32 = 1;
+// 2 is used by clap for usage errors
+const EXIT_VERIFICATION_FAILED: i32 = 3;
+const EXIT_CAPACITY_EXCEEDED: i32 = 4;
+
+#[derive(Parser, Debug)]
+#[clap(name = "zkevm-cli", author, version, about, long_about = None)]
+struct Cli {
+    /// Print the result as json.
+    #[clap(long, global = true)]
+    json: bool,
+    /// Read default options from a json config file.
+    #[clap(long, global = true)]
+    config: Option<PathBuf>,
+    /// Fill missing options from the PARAMS_DIR, SEED_PATH, TRACE_PATH and VK_PATH env vars.
+    #[clap(long, global = true)]
+    from_env: bool,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate a super circuit proof of a batch.
+    Prove(ProveArgs),
+    /// Verify a super circuit or aggregation proof.
+    Verify(VerifyArgs),
+    /// Generate an aggregation proof of a batch.
+    Aggregate(ProveArgs),
+    /// Print a summary of the traces.
+    Inspect(TraceArgs),
+    /// Estimate the row usage of the traces.
+    Estimate(TraceArgs),
+    /// Split the traces into chunks which fit into the circuit.
+    Chunk(ChunkArgs),
+    /// Check that params and seed are usable, optionally mock proving the traces.
+    SelfTest(SelfTestArgs),
+}
+
+#[derive(Args, Debug)]
+struct TraceArgs {
+    /// BlockTrace file or dir, or "-" for stdin.
+    #[clap(short, long)]
+    trace: Option<String>,
+    /// Read stdin as newline-delimited json, one BlockTrace per line.
+    #[clap(long)]
+    ndjson: bool,
+}
+
+#[derive(Args, Debug)]
+struct ProveArgs {
+    #[clap(flatten)]
+    trace: TraceArgs,
+    /// Dir of the params.
+    #[clap(short, long)]
+    params: Option<String>,
+    /// Seed file.
+    #[clap(long)]
+    seed: Option<String>,
+    /// Where to write the proof.
+    #[clap(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct VerifyArgs {
+    /// Dir of the params.
+    #[clap(short, long)]
+    params: Option<String>,
+    /// The proof to verify.
+    #[clap(long)]
+    proof: PathBuf,
+    /// The proof is an aggregation proof.
+    #[clap(long)]
+    agg: bool,
+    /// Aggregation vk, required with --agg.
+    #[clap(long)]
+    vk: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ChunkArgs {
+    #[clap(flatten)]
+    trace: TraceArgs,
+    /// Rows available to every sub-circuit, defaults to the capacity of the circuit.
+    #[clap(long)]
+    max_rows: Option<usize>,
+    /// Max number of blocks in a chunk.
+    #[clap(long)]
+    max_blocks: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+struct SelfTestArgs {
+    #[clap(flatten)]
+    trace: TraceArgs,
+    /// Dir of the params.
+    #[clap(short, long)]
+    params: Option<String>,
+    /// Seed file.
+    #[clap(long)]
+    seed: Option<String>,
+}
+
+/// Defaults read from `--config`, overridden by the command line flags.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Config {
+    params_dir: Option<String>,
+    seed_path: Option<String>,
+    trace_path: Option<String>,
+    vk_path: Option<String>,
+}
+
+impl Config {
+    fn load(path: Option<&Path>, from_env: bool) -> Result<Self> {
+        let mut config: Config = match path {
+            Some(path) => serde_json::from_slice(
+                &fs::read(path).with_context(|| format!("failed to read config {path:?}"))?,
+            )?,
+            None => Config::default(),
+        };
+        if from_env {
+            let env = |name: &'static str| {
+                Some(read_env_var(name, String::new())).filter(|value| !value.is_empty())
+            };
+            config.params_dir = config.params_dir.or_else(|| env("PARAMS_DIR"));
+            config.seed_path = config.seed_path.or_else(|| env("SEED_PATH"));
+            config.trace_path = config.trace_path.or_else(|| env("TRACE_PATH"));
+            config.vk_path = config.vk_path.or_else(|| env("VK_PATH"));
+        }
+        Ok(config)
+    }
+}
+
+/// A proof which was checked and found invalid.
+#[derive(Debug)]
+struct VerificationFailed(String);
+
+impl fmt::Display for VerificationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "verification failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for VerificationFailed {}
+
+fn exit_code(err: &anyhow::Error) -> i32 {
+    if err.downcast_ref::<VerificationFailed>().is_some() {
+        EXIT_VERIFICATION_FAILED
+    } else if err.downcast_ref::<CircuitCapacityExceeded>().is_some() {
+        EXIT_CAPACITY_EXCEEDED
+    } else {
+        EXIT_ERROR
+    }
+}
+
+fn required<'a>(
+    flag: &'a Option<String>,
+    config: &'a Option<String>,
+    name: &str,
+) -> Result<&'a str> {
+    match flag.as_deref().or(config.as_deref()) {
+        Some(value) => Ok(value),
+        None => bail!("missing --{name}"),
+    }
+}
+
+fn load_traces(args: &TraceArgs, config: &Config) -> Result<Vec<BlockTrace>> {
+    let trace_path = required(&args.trace, &config.trace_path, "trace")?;
+    if trace_path == "-" {
+        let format = if args.ndjson {
+            TraceFormat::Ndjson
+        } else {
+            TraceFormat::Json
+        };
+        return TraceSource::Stdin { format }.load(NdjsonErrorPolicy::Abort);
+    }
+    let trace_path = PathBuf::from(trace_path);
+    if !trace_path.is_dir() {
+        return TraceSource::File(trace_path).load(NdjsonErrorPolicy::Abort);
+    }
+    let mut traces = Vec::new();
+    for entry in fs::read_dir(trace_path)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().map_or(false, |ext| ext == "json") {
+            traces.push(get_block_trace_from_file(path));
+        }
+    }
+    sort_traces_by_block_number(&mut traces);
+    Ok(traces)
+}
+
+fn block_number(trace: &BlockTrace) -> u64 {
+    trace.header.number.unwrap_or_default().as_u64()
+}
+
+fn load_prover(params: &Option<String>, seed: &Option<String>, config: &Config) -> Result<Prover> {
+    let params_dir = required(params, &config.params_dir, "params")?;
+    let seed_path = required(seed, &config.seed_path, "seed")?;
+    let params = load_or_create_params(params_dir, *DEGREE)?;
+    let agg_params = load_or_create_params(params_dir, *AGG_DEGREE)?;
+    let seed = load_or_create_seed(seed_path)?;
+    Ok(Prover::from_params_and_seed(params, agg_params, seed))
+}
+
+fn run(cli: &Cli) -> Result<Value> {
+    let config = Config::load(cli.config.as_deref(), cli.from_env)?;
+    match &cli.command {
+        Command::Prove(args) => {
+            let traces = load_traces(&args.trace, &config)?;
+            let mut prover = load_prover(&args.params, &args.seed, &config)?;
+            let mut rng = prover.rng.clone();
+            let proof =
+                prover.create_target_circuit_proof_batch::<SuperCircuit>(&traces, &mut rng)?;
+            prover.dump_target_circuit_proof(
+                &proof,
+                args.output
+                    .to_str()
+                    .context("output path is not valid utf-8")?,
+            )?;
+            Ok(json!({
+                "output": args.output,
+                "vk": vk_fingerprint(&proof.vk),
+                "num_of_proved_blocks": proof.num_of_proved_blocks,
+                "total_num_of_blocks": proof.total_num_of_blocks,
+            }))
+        }
+        Command::Aggregate(args) => {
+            let traces = load_traces(&args.trace, &config)?;
+            let mut prover = load_prover(&args.params, &args.seed, &config)?;
+            let mut rng = prover.rng.clone();
+            let proof = prover.create_agg_circuit_proof_batch(&traces, &mut rng)?;
+            fs::create_dir_all(&args.output)?;
+            proof.dump(&mut args.output.clone())?;
+            Ok(json!({
+                "output": args.output,
+                "vk": vk_fingerprint(&proof.vk),
+                "total_proved_block_count": proof.total_proved_block_count,
+            }))
+        }
+        Command::Verify(args) => {
+            let params_dir = required(&args.params, &config.params_dir, "params")?;
+            let proof = fs::read(&args.proof)
+                .with_context(|| format!("failed to read proof {:?}", args.proof))?;
+            if args.agg {
+                let vk_path = required(&args.vk, &config.vk_path, "vk")?;
+                let verifier = Verifier::from_fpath_with_agg_vk_path(params_dir, vk_path);
+                let proof = serde_json::from_slice::<AggCircuitProof>(&proof)?;
+                if !verifier.verify_agg_circuit_proof(proof)? {
+                    return Err(VerificationFailed("aggregation proof".to_string()).into());
+                }
+            } else {
+                let mut verifier = Verifier::from_fpath(params_dir, None);
+                let proof = serde_json::from_slice::<TargetCircuitProof>(&proof)?;
+                // malformed proof bytes make the transcript panic instead of failing
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    verifier.verify_target_circuit_proof::<SuperCircuit>(&proof)
+                }))
+                .map_err(|_| VerificationFailed("malformed proof".to_string()))?
+                .map_err(|e| VerificationFailed(e.to_string()))?;
+            }
+            Ok(json!({ "proof": args.proof, "verified": true }))
+        }
+        Command::Inspect(args) => {
+            let traces = load_traces(args, &config)?;
+            let blocks: Vec<_> = traces
+                .iter()
+                .map(|trace| {
+                    json!({
+                        "number": block_number(trace),
+                        "hash": trace.header.hash,
+                        "num_txs": trace.transactions.len(),
+                        "gas_used": trace.header.gas_used.as_u64(),
+                        "trace_hash": canonical_trace_hash(trace),
+                    })
+                })
+                .collect();
+            let gas_accounting_error = check_gas_accounting(&traces).err().map(|e| e.to_string());
+            Ok(json!({
+                "blocks": blocks,
+                "gas_accounting_error": gas_accounting_error,
+                "total_gas_used": BatchGasUsage::from_block_traces(&traces).total_gas_used(),
+            }))
+        }
+        Command::Estimate(args) => {
+            let traces = load_traces(args, &config)?;
+            let rows = SuperCircuit::estimate_rows(&traces)?;
+            let measured = RowUsageReport::from_block_traces(&traces)?;
+            let mut per_circuit = vec![0; SUB_CIRCUIT_NAMES.len()];
+            for usage in &measured.per_block {
+                per_circuit
+                    .iter_mut()
+                    .zip(usage)
+                    .for_each(|(acc, rows)| *acc += rows);
+            }
+            let per_circuit: serde_json::Map<_, _> = SUB_CIRCUIT_NAMES
+                .iter()
+                .zip(per_circuit)
+                .map(|(name, rows)| (name.to_string(), json!(rows)))
+                .collect();
+            Ok(json!({
+                "rows": rows,
+                "max_rows": BatchPolicy::default().max_rows,
+                "sub_circuits": per_circuit,
+            }))
+        }
+        Command::Chunk(args) => {
+            let traces = load_traces(&args.trace, &config)?;
+            let default_policy = BatchPolicy::default();
+            let policy = BatchPolicy {
+                max_rows: args.max_rows.unwrap_or(default_policy.max_rows),
+                max_blocks: args.max_blocks.unwrap_or(default_policy.max_blocks),
+                auto_truncate: false,
+                ..default_policy
+            };
+            let measured = RowUsageReport::from_block_traces(&traces)?;
+            let block_numbers: Vec<_> = traces.iter().map(block_number).collect();
+            let chunks: Vec<_> = suggest_rechunk(&block_numbers, &measured, &policy)
+                .into_iter()
+                .map(|range| json!([range.start(), range.end()]))
+                .collect();
+            Ok(json!({ "chunks": chunks }))
+        }
+        Command::SelfTest(args) => {
+            // loading the prover checks that both params come from the same setup
+            let prover = load_prover(&args.params, &args.seed, &config)?;
+            let mut result = json!({
+                "degree": *DEGREE,
+                "agg_degree": *AGG_DEGREE,
+                "config_hash": prover.options.config_hash_hex(),
+            });
+            if args.trace.trace.is_some() || config.trace_path.is_some() {
+                let mut traces = load_traces(&args.trace, &config)?;
+                let policy = BatchPolicy {
+                    auto_truncate: false,
+                    ..Default::default()
+                };
+                check_batch_capacity_with_policy(&mut traces, &policy)?;
+                Prover::mock_prove_target_circuit_batch::<SuperCircuit>(&traces)?;
+                result["mock_proved_blocks"] = json!(traces.len());
+            }
+            Ok(result)
+        }
+    }
+}
+
+fn main() {
+    dotenv::dotenv().ok();
+    env_logger::init();
+
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(output) => {
+            if cli.json {
+                println!("{}", json!({ "ok": true, "result": output }));
+            } else {
+                println!("{}", serde_json::to_string_pretty(&output).unwrap());
+            }
+        }
+        Err(err) => {
+            let code = exit_code(&err);
+            if cli.json {
+                println!(
+                    "{}",
+                    json!({ "ok": false, "error": format!("{err:#}"), "exit_code": code })
+                );
+            } else {
+                eprintln!("error: {err:#}");
+            }
+            process::exit(code);
+        }
+    }
+}
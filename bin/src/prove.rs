@@ -11,7 +11,10 @@ use std::time::Instant;
 use zkevm::{
     circuit::{SuperCircuit, AGG_DEGREE, DEGREE},
     prover::Prover,
-    utils::{get_block_trace_from_file, load_or_create_params, load_or_create_seed},
+    utils::{
+        get_block_trace_from_file, load_or_create_params, load_or_create_seed, NdjsonErrorPolicy,
+        TraceFormat, TraceSource,
+    },
 };
 
 #[derive(Parser, Debug)]
@@ -23,9 +26,12 @@ struct Args {
     /// Get seed and write into file.
     #[clap(long = "seed")]
     seed_path: Option<String>,
-    /// Get BlockTrace from file or dir.
+    /// Get BlockTrace from file or dir, or from stdin if "-".
     #[clap(short, long = "trace")]
     trace_path: Option<String>,
+    /// Read stdin as newline-delimited json, one BlockTrace per line.
+    #[clap(long = "ndjson")]
+    ndjson: bool,
     /// Option means if generates super circuit proof.
     /// Boolean means if output super circuit proof.
     #[clap(long = "super")]
@@ -63,7 +69,19 @@ fn main() {
 
     let mut traces = HashMap::new();
     let trace_path = PathBuf::from(&args.trace_path.unwrap());
-    if trace_path.is_dir() {
+    if trace_path.to_str() == Some("-") {
+        let format = if args.ndjson {
+            TraceFormat::Ndjson
+        } else {
+            TraceFormat::Json
+        };
+        let block_traces = TraceSource::Stdin { format }
+            .load(NdjsonErrorPolicy::Abort)
+            .expect("failed to read traces from stdin");
+        for (i, block_trace) in block_traces.into_iter().enumerate() {
+            traces.insert(format!("stdin_{}", i).into(), block_trace);
+        }
+    } else if trace_path.is_dir() {
         for entry in fs::read_dir(trace_path).unwrap() {
             let path = entry.unwrap().path();
             if path.is_file() && path.to_str().unwrap().ends_with(".json") {
@@ -2,6 +2,7 @@ use clap::Parser;
 use log::info;
 use std::fs::File;
 use std::io::Read;
+use zkevm::io::pem_to_vk;
 use zkevm::prover::{AggCircuitProof, TargetCircuitProof};
 use zkevm::verifier::Verifier;
 use zkevm::{
@@ -18,6 +19,9 @@ struct Args {
     /// Get vk from the file.
     #[clap(long = "vk")]
     vk_path: Option<String>,
+    /// Format of the vk file, "raw" or "pem".
+    #[clap(long = "format", default_value = "raw")]
+    vk_format: String,
     /// the path of super circuit proof to verify.
     #[clap(long = "super")]
     super_proof: Option<String>,
@@ -36,6 +40,12 @@ fn main() {
     let agg_params = load_or_create_params(&args.params_path.unwrap(), *AGG_DEGREE)
         .expect("failed to load or create params");
     let agg_vk = read_from_file(&args.vk_path.unwrap());
+    let agg_vk = match args.vk_format.as_str() {
+        "raw" => agg_vk,
+        "pem" => pem_to_vk(std::str::from_utf8(&agg_vk).expect("pem vk is not utf-8"))
+            .expect("failed to decode pem vk"),
+        format => panic!("unknown vk format {format}"),
+    };
 
     let mut v = Verifier::from_params(params, agg_params, Some(agg_vk));
     if let Some(path) = args.super_proof {
@@ -0,0 +1,116 @@
+use assert_cmd::Command;
+use mock_plonk::{prove_mock_plonk, StandardPlonk};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde_json::Value;
+use zkevm::prover::Prover;
+use zkevm::utils::load_or_create_params;
+
+#[allow(dead_code)]
+#[path = "../../zkevm/tests/mock_plonk.rs"]
+mod mock_plonk;
+
+const PARAMS_DIR: &str = "../zkevm/test_params";
+const SEED_PATH: &str = "../zkevm/test_seed";
+const GREETER_TRACE: &str = "../zkevm/tests/traces/greeter.json";
+/// Degree of the params used by the tests which don't prove a block.
+const SMALL_DEGREE: usize = 8;
+
+fn cli() -> Command {
+    Command::cargo_bin("zkevm-cli").unwrap()
+}
+
+fn json_output(output: &[u8]) -> Value {
+    serde_json::from_slice(output).unwrap()
+}
+
+#[test]
+fn test_estimate() {
+    let output = cli()
+        .args(["--json", "estimate", "--trace", GREETER_TRACE])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = json_output(&output);
+    assert_eq!(output["ok"], true);
+    assert!(output["result"]["rows"].as_u64().unwrap() > 0);
+    assert!(output["result"]["sub_circuits"]["evm"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn test_missing_trace() {
+    let output = cli()
+        .args(["--json", "inspect"])
+        .assert()
+        .code(1)
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(json_output(&output)["ok"], false);
+}
+
+#[test]
+fn test_usage_error() {
+    cli().args(["prove", "--bogus"]).assert().code(2);
+}
+
+#[test]
+fn test_verification_failed() {
+    let params_dir = std::env::temp_dir().join("zkevm_cli_small_params");
+    let params_dir = params_dir.to_str().unwrap();
+    let params = load_or_create_params(params_dir, SMALL_DEGREE).unwrap();
+
+    // a valid proof, but of another circuit than the super circuit the cli verifies
+    let mut rng = XorShiftRng::from_seed([0u8; 16]);
+    let mut prover = Prover::from_params_and_seed(params.clone(), params, [0u8; 16]);
+    let circuit = StandardPlonk::rand(&mut rng);
+    let proof = prove_mock_plonk(&mut prover, circuit, &mut rng);
+    let proof_path = std::env::temp_dir().join("zkevm_cli_standard_plonk.proof");
+    let proof_path = proof_path.to_str().unwrap();
+    proof.dump_to_file(proof_path).unwrap();
+
+    let output = cli()
+        .env("DEGREE", SMALL_DEGREE.to_string())
+        .env("AGG_DEGREE", SMALL_DEGREE.to_string())
+        .args([
+            "--json", "verify", "--params", params_dir, "--proof", proof_path,
+        ])
+        .assert()
+        .code(3)
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(json_output(&output)["ok"], false);
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_prove_verify() {
+    let proof_path = std::env::temp_dir().join("zkevm_cli_greeter.proof");
+    let proof_path = proof_path.to_str().unwrap();
+    cli()
+        .args(["prove", "--trace", GREETER_TRACE])
+        .args([
+            "--params", PARAMS_DIR, "--seed", SEED_PATH, "--output", proof_path,
+        ])
+        .assert()
+        .success();
+
+    cli()
+        .args(["verify", "--params", PARAMS_DIR, "--proof", proof_path])
+        .assert()
+        .success();
+
+    // corrupt the proof bytes, keeping the json valid
+    let mut proof: Value = serde_json::from_slice(&std::fs::read(proof_path).unwrap()).unwrap();
+    let bytes = proof["snark"]["proof"].as_array_mut().unwrap();
+    bytes[0] = Value::from((bytes[0].as_u64().unwrap() + 1) % 256);
+    std::fs::write(proof_path, serde_json::to_vec(&proof).unwrap()).unwrap();
+
+    cli()
+        .args(["verify", "--params", PARAMS_DIR, "--proof", proof_path])
+        .assert()
+        .code(3);
+}
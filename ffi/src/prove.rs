@@ -14,7 +14,7 @@ pub unsafe extern "C" fn init_prover(params_path: *const c_char, seed_path: *con
 
     let params_path = c_char_to_str(params_path);
     let seed_path = c_char_to_str(seed_path);
-    let p = Prover::from_fpath(params_path, seed_path);
+    let p = Prover::from_fpath(params_path, Some(seed_path));
     PROVER.set(p).unwrap();
 }
 
@@ -1,20 +1,44 @@
 use eth_types::evm_types::{Gas, GasCost, OpcodeId, ProgramCounter, Stack, Storage};
 use eth_types::{Block, GethExecStep, GethExecTrace, Hash, Transaction, Word, H256};
+use ethers_core::types::transaction::eip2930::{AccessList, AccessListItem};
 use ethers_core::types::{Address, Bytes, U256, U64};
+use serde::de::{IgnoredAny, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
 
-fn u64_to_word<'de, D>(deserializer: D) -> Result<U256, D::Error>
+/// Accept a word written either as a json number, as geth does, or as a hex string, as
+/// `U256` serializes.
+fn u64_or_hex_to_word<'de, D>(deserializer: D) -> Result<U256, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let i: u64 = Deserialize::deserialize(deserializer)?;
-    Ok((i).into())
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum U64OrWord {
+        U64(u64),
+        Word(U256),
+    }
+    Ok(match Deserialize::deserialize(deserializer)? {
+        U64OrWord::U64(i) => i.into(),
+        U64OrWord::Word(word) => word,
+    })
+}
+
+fn null_to_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    let v: Option<T> = Deserialize::deserialize(deserializer)?;
+    Ok(v.unwrap_or_default())
 }
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
 pub struct BlockTrace {
-    #[serde(rename = "chainID", default, deserialize_with = "u64_to_word")]
+    #[serde(rename = "chainID", default, deserialize_with = "u64_or_hex_to_word")]
     pub chain_id: U256,
     pub coinbase: AccountProofWrapper,
     pub header: EthBlock,
@@ -59,6 +83,12 @@ pub struct TransactionTrace {
     pub gas: u64,
     #[serde(rename = "gasPrice")]
     pub gas_price: U256,
+    /// EIP-1559 fee cap, only set for dynamic fee (type 2) transactions.
+    #[serde(rename = "gasFeeCap", default)]
+    pub max_fee_per_gas: Option<U256>,
+    /// EIP-1559 priority fee cap, only set for dynamic fee (type 2) transactions.
+    #[serde(rename = "gasTipCap", default)]
+    pub max_priority_fee_per_gas: Option<U256>,
     pub from: Address,
     pub to: Option<Address>,
     #[serde(rename = "chainId")]
@@ -67,6 +97,9 @@ pub struct TransactionTrace {
     pub data: Bytes,
     #[serde(rename = "isCreate")]
     pub is_create: bool,
+    /// EIP-2930 access list, empty for legacy transactions.
+    #[serde(rename = "accessList", default, deserialize_with = "null_to_default")]
+    pub access_list: Vec<AccessListEntry>,
     pub v: U64,
     pub r: U256,
     pub s: U256,
@@ -94,16 +127,42 @@ impl TransactionTrace {
             v: self.v,
             r: self.r,
             s: self.s,
-            transaction_type: None,
-            access_list: None,
-            max_priority_fee_per_gas: None,
-            max_fee_per_gas: None,
+            // Only EIP-2930 and EIP-1559 transactions are typed for the circuits, legacy and
+            // L1 message transactions stay untyped. Typed transactions carry an access list,
+            // possibly empty.
+            transaction_type: if matches!(self.type_, 1 | 2) {
+                Some(U64::from(self.type_))
+            } else {
+                None
+            },
+            access_list: if !matches!(self.type_, 1 | 2) {
+                None
+            } else {
+                Some(AccessList(
+                    self.access_list
+                        .iter()
+                        .map(|entry| AccessListItem {
+                            address: entry.address,
+                            storage_keys: entry.storage_keys.clone(),
+                        })
+                        .collect(),
+                ))
+            },
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            max_fee_per_gas: self.max_fee_per_gas,
             chain_id: Some(self.chain_id),
             other: Default::default(),
         }
     }
 }
 
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct AccessListEntry {
+    pub address: Address,
+    #[serde(rename = "storageKeys", default)]
+    pub storage_keys: Vec<H256>,
+}
+
 pub type AccountTrieProofs = HashMap<Address, Vec<Bytes>>;
 pub type StorageTrieProofs = HashMap<Address, HashMap<Word, Vec<Bytes>>>;
 
@@ -236,3 +295,150 @@ pub struct StorageProofWrapper {
     pub value: Option<U256>,
     pub proof: Option<Vec<Bytes>>,
 }
+
+/// A `BlockTrace` without the per-step data, for workloads which only estimate, validate or
+/// report the cost of blocks. Use [`BlockTraceLite::upgrade`] to get the full trace.
+///
+/// The row estimators still take full traces, as estimating rows needs the witness block.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BlockTraceLite {
+    #[serde(rename = "chainID", default, deserialize_with = "u64_or_hex_to_word")]
+    pub chain_id: U256,
+    pub header: EthBlock,
+    pub transactions: Vec<TransactionTrace>,
+    #[serde(rename = "executionResults")]
+    pub execution_results: Vec<ExecutionResultLite>,
+    #[serde(rename = "storageTrace")]
+    pub storage_trace: StorageTraceLite,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BlockTraceLiteJsonRpcResult {
+    pub result: BlockTraceLite,
+}
+
+impl BlockTraceLite {
+    /// Re-read the full trace this lite trace was parsed from.
+    pub fn upgrade<P: AsRef<Path>>(&self, path: P) -> io::Result<BlockTrace> {
+        let buffer = std::fs::read(path)?;
+        let trace = serde_json::from_slice::<BlockTrace>(&buffer)
+            .or_else(|_| {
+                serde_json::from_slice::<BlockTraceJsonRpcResult>(&buffer).map(|r| r.result)
+            })
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if trace.header.hash != self.header.hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "trace of block {:?} changed, was {:?}",
+                    trace.header.hash, self.header.hash
+                ),
+            ));
+        }
+        Ok(trace)
+    }
+
+    pub fn num_steps(&self) -> usize {
+        self.execution_results
+            .iter()
+            .flat_map(|result| result.opcode_counts.values())
+            .sum()
+    }
+}
+
+/// An `ExecutionResult` where the struct logs are reduced to opcode counts.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExecutionResultLite {
+    pub gas: u64,
+    pub failed: bool,
+    #[serde(rename = "structLogs", deserialize_with = "count_opcodes")]
+    pub opcode_counts: HashMap<OpcodeId, usize>,
+}
+
+/// Only the opcode of a struct log, the other fields are skipped without being materialized.
+#[derive(Deserialize)]
+struct StepOpcode {
+    op: OpcodeId,
+}
+
+fn count_opcodes<'de, D>(deserializer: D) -> Result<HashMap<OpcodeId, usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct CountVisitor;
+
+    impl<'de> Visitor<'de> for CountVisitor {
+        type Value = HashMap<OpcodeId, usize>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of struct logs")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut counts = HashMap::new();
+            while let Some(step) = seq.next_element::<StepOpcode>()? {
+                *counts.entry(step.op).or_insert(0) += 1;
+            }
+            Ok(counts)
+        }
+    }
+
+    deserializer.deserialize_seq(CountVisitor)
+}
+
+/// A `StorageTrace` where the proofs are reduced to their number of nodes.
+#[derive(Deserialize, Debug, Clone)]
+pub struct StorageTraceLite {
+    #[serde(rename = "rootBefore")]
+    pub root_before: Hash,
+    #[serde(rename = "rootAfter")]
+    pub root_after: Hash,
+    #[serde(
+        rename = "proofs",
+        default,
+        deserialize_with = "count_account_proof_nodes"
+    )]
+    pub account_proof_nodes: usize,
+    #[serde(
+        rename = "storageProofs",
+        default,
+        deserialize_with = "count_storage_proof_nodes"
+    )]
+    pub storage_proof_nodes: usize,
+    #[serde(
+        rename = "deletionProofs",
+        default,
+        deserialize_with = "count_deletion_proof_nodes"
+    )]
+    pub deletion_proof_nodes: usize,
+}
+
+fn count_account_proof_nodes<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let proofs: Option<HashMap<Address, Vec<IgnoredAny>>> = Deserialize::deserialize(deserializer)?;
+    Ok(proofs.iter().flatten().map(|(_, nodes)| nodes.len()).sum())
+}
+
+fn count_storage_proof_nodes<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let proofs: Option<HashMap<Address, HashMap<Word, Vec<IgnoredAny>>>> =
+        Deserialize::deserialize(deserializer)?;
+    Ok(proofs
+        .iter()
+        .flatten()
+        .flat_map(|(_, slots)| slots.values())
+        .map(Vec::len)
+        .sum())
+}
+
+fn count_deletion_proof_nodes<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let nodes: Option<Vec<IgnoredAny>> = Deserialize::deserialize(deserializer)?;
+    Ok(nodes.map_or(0, |nodes| nodes.len()))
+}
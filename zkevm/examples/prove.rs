@@ -10,11 +10,10 @@ use zkevm::{
 };
 
 use git_version::git_version;
-use glob::glob;
 use std::sync::Once;
 use types::eth::BlockTrace;
-use zkevm::utils::get_block_trace_from_file;
 use zkevm::utils::read_env_var;
+use zkevm::utils::{get_block_trace_from_file, load_batch_traces_bounded};
 
 pub const GIT_VERSION: &str = git_version!();
 pub const PARAMS_DIR: &str = "./zkevm/test_params";
@@ -34,31 +33,9 @@ pub fn init() {
 }
 
 pub fn load_batch_traces(batch_dir: &str) -> (Vec<String>, Vec<types::eth::BlockTrace>) {
-    let file_names: Vec<String> = glob(&format!("{batch_dir}/**/*.json"))
-        .unwrap()
-        .map(|p| p.unwrap().to_str().unwrap().to_string())
-        .collect();
-    log::info!("test batch with {:?}", file_names);
-    let mut names_and_traces = file_names
-        .into_iter()
-        .map(|trace_path| {
-            let trace: BlockTrace = get_block_trace_from_file(trace_path.clone());
-            (
-                trace_path,
-                trace.clone(),
-                trace.header.number.unwrap().as_u64(),
-            )
-        })
-        .collect::<Vec<_>>();
-    names_and_traces.sort_by(|a, b| a.2.cmp(&b.2));
-    log::info!(
-        "sorted: {:?}",
-        names_and_traces
-            .iter()
-            .map(|(f, _, _)| f.clone())
-            .collect::<Vec<String>>()
-    );
-    names_and_traces.into_iter().map(|(f, t, _)| (f, t)).unzip()
+    let max_batch_size_mb = read_env_var("MAX_BATCH_SIZE_MB", usize::MAX >> 20);
+    load_batch_traces_bounded(batch_dir, max_batch_size_mb.saturating_mul(1 << 20))
+        .unwrap_or_else(|e| panic!("failed to load batch {batch_dir}: {e}"))
 }
 
 pub fn parse_trace_path_from_mode(mode: &str) -> &'static str {
@@ -111,7 +88,13 @@ fn test_target_circuit_prove_verify<C: TargetCircuit>() {
 
     log::info!("start generating {} proof", C::name());
     let now = Instant::now();
-    let mut prover = Prover::from_fpath(PARAMS_DIR, SEED_PATH);
+    let allow_random_seed = read_env_var("ALLOW_RANDOM_SEED", false);
+    let seed_path = if allow_random_seed && !std::path::Path::new(SEED_PATH).exists() {
+        None
+    } else {
+        Some(SEED_PATH)
+    };
+    let mut prover = Prover::from_fpath(PARAMS_DIR, seed_path);
     let proof = prover
         .create_target_circuit_proof_batch::<C>(&block_traces, &mut rng)
         .unwrap();
@@ -135,4 +118,4 @@ fn test_target_circuit_prove_verify<C: TargetCircuit>() {
 
 pub fn main() {
     test_target_circuit_prove_verify::<SuperCircuit>();
-}
\ No newline at end of file
+}
@@ -0,0 +1,190 @@
+//! Attestations of vk generation, so that third parties can reproduce a published vk from
+//! the same params and sources.
+
+use crate::circuit::{
+    TargetCircuit, CHAIN_ID, MAX_BYTECODE, MAX_CALLDATA, MAX_EXP_STEPS, MAX_INNER_BLOCKS,
+    MAX_KECCAK_ROWS, MAX_MPT_ROWS, MAX_RWS, MAX_TXS,
+};
+use crate::io::serialize_vk;
+use crate::utils::DEFAULT_SERDE_FORMAT;
+use git_version::git_version;
+use halo2_proofs::halo2curves::bn256::Bn256;
+use halo2_proofs::plonk::keygen_vk;
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+pub const GIT_VERSION: &str = git_version!(fallback = "unknown");
+
+/// Claim of every attestation. keygen only depends on the params and the circuit
+/// configuration: fixed columns and permutations are assigned in circuit order, and the
+/// parallel parts of halo2 write to disjoint, index-addressed chunks.
+pub const DETERMINISM_CLAIM: &str =
+    "the vk only depends on the params file and the circuit parameters and sources";
+
+/// Parameters the circuit is compiled with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitParameters {
+    pub degree: u32,
+    /// sha256 of the chain id as 8 big-endian bytes.
+    pub chain_config_hash: String,
+    pub max_txs: usize,
+    pub max_inner_blocks: usize,
+    pub max_calldata: usize,
+    pub max_bytecode: usize,
+    pub max_rws: usize,
+    pub max_exp_steps: usize,
+    pub max_mpt_rows: usize,
+    pub max_keccak_rows: usize,
+}
+
+impl CircuitParameters {
+    /// The parameters of this build, for params of `degree`.
+    pub fn current(degree: u32) -> Self {
+        Self {
+            degree,
+            chain_config_hash: hex::encode(Sha256::digest(CHAIN_ID.to_be_bytes())),
+            max_txs: MAX_TXS,
+            max_inner_blocks: MAX_INNER_BLOCKS,
+            max_calldata: MAX_CALLDATA,
+            max_bytecode: MAX_BYTECODE,
+            max_rws: MAX_RWS,
+            max_exp_steps: MAX_EXP_STEPS,
+            max_mpt_rows: MAX_MPT_ROWS,
+            max_keccak_rows: MAX_KECCAK_ROWS,
+        }
+    }
+}
+
+/// Everything a vk was generated from. Fields serialize in declaration order, so the json
+/// bytes of an attestation are stable and can be signed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VkAttestation {
+    pub circuit_name: String,
+    pub circuit_parameters: CircuitParameters,
+    pub params_sha256: String,
+    pub crate_version: String,
+    pub git_version: String,
+    pub vk_sha256: String,
+    pub determinism_claim: String,
+}
+
+impl VkAttestation {
+    /// The compact json bytes to sign.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+}
+
+/// The locally generated vk or build does not match an attestation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestationMismatch {
+    pub field: &'static str,
+    pub attested: String,
+    pub local: String,
+}
+
+impl fmt::Display for AttestationMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "attestation mismatch on {}: attested {}, local {}",
+            self.field, self.attested, self.local
+        )
+    }
+}
+
+impl std::error::Error for AttestationMismatch {}
+
+fn read_params(params_bytes: &[u8]) -> anyhow::Result<ParamsKZG<Bn256>> {
+    Ok(ParamsKZG::<Bn256>::read_custom(
+        &mut &params_bytes[..],
+        DEFAULT_SERDE_FORMAT,
+    )?)
+}
+
+/// Generate the vk of circuit `C` from the params file at `params_path`.
+pub fn generate_vk<C: TargetCircuit>(params_path: &str) -> anyhow::Result<Vec<u8>> {
+    let params = read_params(&std::fs::read(params_path)?)?;
+    let vk = keygen_vk(&params, &C::dummy_inner_circuit())?;
+    Ok(serialize_vk(&vk))
+}
+
+/// Generate the vk of circuit `C` from the params file at `params_path`, and attest how it was
+/// generated.
+pub fn generate_vk_attestation<C: TargetCircuit>(
+    params_path: &str,
+) -> anyhow::Result<VkAttestation> {
+    let params_bytes = std::fs::read(params_path)?;
+    let params = read_params(&params_bytes)?;
+    let vk = serialize_vk(&keygen_vk(&params, &C::dummy_inner_circuit())?);
+
+    Ok(VkAttestation {
+        circuit_name: C::name(),
+        circuit_parameters: CircuitParameters::current(params.k()),
+        params_sha256: hex::encode(Sha256::digest(&params_bytes)),
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_version: GIT_VERSION.to_string(),
+        vk_sha256: hex::encode(Sha256::digest(&vk)),
+        determinism_claim: DETERMINISM_CLAIM.to_string(),
+    })
+}
+
+/// Check a vk generated by the reproducing party for circuit `C` from the params file at
+/// `params_path`, and the parameters of its build, against an attestation. Mismatches are
+/// returned as [`AttestationMismatch`].
+pub fn verify_vk_attestation<C: TargetCircuit>(
+    attestation: &VkAttestation,
+    params_path: &str,
+    locally_generated_vk: &[u8],
+) -> anyhow::Result<()> {
+    let params_bytes = std::fs::read(params_path)?;
+    let params = read_params(&params_bytes)?;
+    let attested = &attestation.circuit_parameters;
+    let local = CircuitParameters::current(params.k());
+    macro_rules! parameter {
+        ($field:ident) => {
+            (
+                stringify!($field),
+                attested.$field.to_string(),
+                local.$field.to_string(),
+            )
+        };
+    }
+    let fields = [
+        ("circuit_name", attestation.circuit_name.clone(), C::name()),
+        parameter!(degree),
+        (
+            "params_sha256",
+            attestation.params_sha256.clone(),
+            hex::encode(Sha256::digest(&params_bytes)),
+        ),
+        parameter!(chain_config_hash),
+        parameter!(max_txs),
+        parameter!(max_inner_blocks),
+        parameter!(max_calldata),
+        parameter!(max_bytecode),
+        parameter!(max_rws),
+        parameter!(max_exp_steps),
+        parameter!(max_mpt_rows),
+        parameter!(max_keccak_rows),
+        (
+            "vk_sha256",
+            attestation.vk_sha256.clone(),
+            hex::encode(Sha256::digest(locally_generated_vk)),
+        ),
+    ];
+    for (field, attested, local) in fields {
+        if attested != local {
+            return Err(AttestationMismatch {
+                field,
+                attested,
+                local,
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
@@ -1,18 +1,35 @@
 use halo2_proofs::halo2curves::bn256::Fr;
 use once_cell::sync::Lazy;
+use serde_derive::Serialize;
 use snark_verifier_sdk::CircuitExt;
 use types::eth::BlockTrace;
 use zkevm_circuits::witness;
 
 mod builder;
+mod constraint_summary;
+mod l1_messages;
 mod super_circuit;
+mod test_vectors;
 pub use super_circuit::SuperCircuit;
+pub use test_vectors::{
+    generate_test_vectors, verify_against_test_vector_digests, verify_against_test_vectors,
+    Mismatch, TestVectorDigests, TestVectors,
+};
 
 use crate::utils::read_env_var;
 
 pub use self::builder::{
     block_traces_to_witness_block, calculate_row_usage_of_trace,
-    calculate_row_usage_of_witness_block, check_batch_capacity, SUB_CIRCUIT_NAMES,
+    calculate_row_usage_of_witness_block, check_batch_capacity, check_batch_capacity_with_policy,
+    check_calldata_size, check_fee_caps, check_gas_accounting, suggest_rechunk, BatchGasUsage,
+    BatchPolicy, BlockGasUsage, CalldataTooLarge, CircuitCapacityExceeded, FeeCapBelowBaseFee,
+    GasAccountingMismatch, RowUsageReport, DYNAMIC_FEE_TX_TYPE, SUB_CIRCUIT_NAMES,
+};
+pub use self::constraint_summary::{
+    constraint_summary, write_constraint_summary, ConstraintSummary, GateSummary, LookupSummary,
+};
+pub use self::l1_messages::{
+    L1MessageAccounting, L1MessageAccountingError, L1MessageQueueSnapshot, L1_MESSAGE_TX_TYPE,
 };
 
 ////// params for degree = 19 ////////////
@@ -26,19 +43,46 @@ const MAX_EXP_STEPS: usize = 10_000;
 */
 
 ////// params for degree = 20 ////////////
-pub static DEGREE: Lazy<usize> = Lazy::new(|| read_env_var("DEGREE", 20));
-const MAX_TXS: usize = 32;
-const MAX_INNER_BLOCKS: usize = 100;
-const MAX_EXP_STEPS: usize = 10_000;
-const MAX_CALLDATA: usize = 400_000;
-const MAX_BYTECODE: usize = 400_000;
-const MAX_MPT_ROWS: usize = 400_000;
-const MAX_KECCAK_ROWS: usize = 524_000;
-const MAX_RWS: usize = 1_000_000;
+pub(crate) const DEFAULT_DEGREE: usize = 20;
+pub static DEGREE: Lazy<usize> = Lazy::new(|| read_env_var("DEGREE", DEFAULT_DEGREE));
+pub(crate) const MAX_TXS: usize = 32;
+pub(crate) const MAX_INNER_BLOCKS: usize = 100;
+pub(crate) const MAX_EXP_STEPS: usize = 10_000;
+pub(crate) const MAX_CALLDATA: usize = 400_000;
+pub(crate) const MAX_BYTECODE: usize = 400_000;
+pub(crate) const MAX_MPT_ROWS: usize = 400_000;
+pub(crate) const MAX_KECCAK_ROWS: usize = 524_000;
+pub(crate) const MAX_RWS: usize = 1_000_000;
 
 pub static CHAIN_ID: Lazy<u64> = Lazy::new(|| read_env_var("CHAIN_ID", 0x82751));
 pub static AGG_DEGREE: Lazy<usize> = Lazy::new(|| read_env_var("AGG_DEGREE", 26));
 pub static AUTO_TRUNCATE: Lazy<bool> = Lazy::new(|| read_env_var("AUTO_TRUNCATE", true));
+/// Measure the row usage of independent blocks with rayon, see [`RowUsageReport::measure`].
+pub static PARALLEL_WITNESS: Lazy<bool> = Lazy::new(|| read_env_var("PARALLEL_WITNESS", false));
+
+/// Smallest `k` with `2^k >= rows`.
+pub(crate) const fn log2_ceil(rows: usize) -> u32 {
+    rows.next_power_of_two().trailing_zeros()
+}
+
+/// Structured metadata of a target circuit, for tools which need to discover what it proves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CircuitDescription {
+    pub name: String,
+    pub version: &'static str,
+    pub degree: u32,
+    pub sub_circuits: Vec<&'static str>,
+    pub supported_eip_features: Vec<&'static str>,
+    pub public_inputs: Vec<PublicInputField>,
+}
+
+/// A public input of a circuit, in the order of the instance column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublicInputField {
+    pub name: &'static str,
+    /// Number of field elements the input takes.
+    pub len: usize,
+}
 
 /// A target circuit trait is a wrapper of inner circuit, with convenient APIs for building
 /// circuits from traces.
@@ -96,4 +140,31 @@ pub trait TargetCircuit {
     fn public_input_len() -> usize {
         0
     }
+
+    /// Minimum params degree `k` whose `2^k` rows fit the static row budget of the circuit. By
+    /// default, the degree the `MAX_*` limits are sized for, whatever `DEGREE` is set to.
+    fn required_params_degree() -> u32 {
+        DEFAULT_DEGREE as u32
+    }
+
+    /// Describe the circuit. By default, only the name, the degree and the length of the
+    /// public inputs are known.
+    fn describe() -> CircuitDescription {
+        let public_input_len = Self::public_input_len();
+        CircuitDescription {
+            name: Self::name(),
+            version: env!("CARGO_PKG_VERSION"),
+            degree: *DEGREE as u32,
+            sub_circuits: vec![],
+            supported_eip_features: vec![],
+            public_inputs: if public_input_len == 0 {
+                vec![]
+            } else {
+                vec![PublicInputField {
+                    name: "instance",
+                    len: public_input_len,
+                }]
+            },
+        }
+    }
 }
@@ -1,18 +1,21 @@
 use super::{MAX_CALLDATA, MAX_EXP_STEPS, MAX_RWS, MAX_TXS};
 use crate::circuit::{
     TargetCircuit, AUTO_TRUNCATE, CHAIN_ID, DEGREE, MAX_BYTECODE, MAX_INNER_BLOCKS,
-    MAX_KECCAK_ROWS, MAX_MPT_ROWS,
+    MAX_KECCAK_ROWS, MAX_MPT_ROWS, PARALLEL_WITNESS,
 };
 use anyhow::bail;
 use bus_mapping::circuit_input_builder::{self, BlockHead, CircuitInputBuilder, CircuitsParams};
 use bus_mapping::state_db::{Account, CodeDB, StateDB};
 use eth_types::evm_types::OpcodeId;
-use eth_types::ToAddress;
+use eth_types::{ToAddress, H256};
 use ethers_core::types::{Bytes, U256};
 use halo2_proofs::halo2curves::bn256::Fr;
 use is_even::IsEven;
 use itertools::Itertools;
 use mpt_zktrie::state::ZktrieState;
+use rayon::prelude::*;
+use std::fmt;
+use std::ops::RangeInclusive;
 use std::time::Instant;
 use types::eth::{BlockTrace, EthBlock, ExecStep};
 use zkevm_circuits::evm_circuit::witness::block_apply_mpt_state;
@@ -51,10 +54,309 @@ pub fn calculate_row_usage_of_witness_block(
     Ok(rows)
 }
 
+/// Gas used by a block, as computed from its execution results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockGasUsage {
+    pub block: u64,
+    pub gas_used: u64,
+}
+
+/// Gas used by each block of a batch, computed from the same execution results
+/// the evm circuit is built from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchGasUsage {
+    pub per_block: Vec<BlockGasUsage>,
+}
+
+impl BatchGasUsage {
+    pub fn from_block_traces(block_traces: &[BlockTrace]) -> Self {
+        let per_block = block_traces
+            .iter()
+            .map(|block_trace| BlockGasUsage {
+                block: block_number_of_trace(block_trace),
+                gas_used: computed_gas_used(block_trace),
+            })
+            .collect();
+        Self { per_block }
+    }
+
+    pub fn total_gas_used(&self) -> u64 {
+        self.per_block.iter().map(|block| block.gas_used).sum()
+    }
+}
+
+/// The gas used by the transactions of a block does not add up to the gas used in its header.
+/// Such a trace is a bug of the trace dump and must not be proved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasAccountingMismatch {
+    pub block: u64,
+    pub header: u64,
+    pub computed: u64,
+}
+
+impl fmt::Display for GasAccountingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "gas accounting mismatch in block {}: header gasUsed {}, sum of tx gas {}",
+            self.block, self.header, self.computed
+        )
+    }
+}
+
+impl std::error::Error for GasAccountingMismatch {}
+
+fn computed_gas_used(block_trace: &BlockTrace) -> u64 {
+    block_trace
+        .execution_results
+        .iter()
+        .map(|result| result.gas)
+        .sum()
+}
+
+/// Check that the gas of the transactions of every block adds up to the header's gasUsed.
+pub fn check_gas_accounting(block_traces: &[BlockTrace]) -> Result<(), GasAccountingMismatch> {
+    for block_trace in block_traces {
+        let header = block_trace.header.gas_used.as_u64();
+        let computed = computed_gas_used(block_trace);
+        if header != computed {
+            return Err(GasAccountingMismatch {
+                block: block_number_of_trace(block_trace),
+                header,
+                computed,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The calldata of a batch does not fit in the calldata table.
+///
+/// `tx_hash` and `len` describe the transaction whose calldata crosses the limit, `total` is
+/// the calldata of the batch up to and including that transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalldataTooLarge {
+    pub tx_hash: H256,
+    pub len: usize,
+    pub total: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for CalldataTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "calldata of the batch reaches {} bytes at tx {:?} ({} bytes), limit is {}",
+            self.total, self.tx_hash, self.len, self.limit
+        )
+    }
+}
+
+impl std::error::Error for CalldataTooLarge {}
+
+/// Check that the calldata of all transactions of the batch fits in `MAX_CALLDATA`.
+pub fn check_calldata_size(block_traces: &[BlockTrace]) -> Result<(), CalldataTooLarge> {
+    let mut total = 0;
+    for tx in block_traces
+        .iter()
+        .flat_map(|block_trace| &block_trace.transactions)
+    {
+        total += tx.data.len();
+        if total > MAX_CALLDATA {
+            return Err(CalldataTooLarge {
+                tx_hash: tx.tx_hash,
+                len: tx.data.len(),
+                total,
+                limit: MAX_CALLDATA,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Transaction type of EIP-1559 dynamic fee transactions.
+pub const DYNAMIC_FEE_TX_TYPE: u8 = 2;
+
+/// The fee cap of a dynamic fee transaction is below the base fee of its block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeCapBelowBaseFee {
+    pub tx_hash: H256,
+    pub max_fee_per_gas: U256,
+    pub base_fee_per_gas: U256,
+}
+
+impl fmt::Display for FeeCapBelowBaseFee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "max fee per gas {} of tx {:?} is below the base fee {}",
+            self.max_fee_per_gas, self.tx_hash, self.base_fee_per_gas
+        )
+    }
+}
+
+impl std::error::Error for FeeCapBelowBaseFee {}
+
+/// Check that every dynamic fee transaction pays at least the base fee of its block, as the
+/// fee computation of the circuits assumes. Blocks without a base fee are pre-London and
+/// skipped. Traces without a fee cap fall back to the gas price.
+pub fn check_fee_caps(block_traces: &[BlockTrace]) -> Result<(), FeeCapBelowBaseFee> {
+    for block_trace in block_traces {
+        let base_fee_per_gas = match block_trace.header.base_fee_per_gas {
+            Some(base_fee_per_gas) => base_fee_per_gas,
+            None => continue,
+        };
+        let below = block_trace
+            .transactions
+            .iter()
+            .filter(|tx| tx.type_ == DYNAMIC_FEE_TX_TYPE)
+            .map(|tx| (tx, tx.max_fee_per_gas.unwrap_or(tx.gas_price)))
+            .find(|(_, max_fee_per_gas)| *max_fee_per_gas < base_fee_per_gas);
+        if let Some((tx, max_fee_per_gas)) = below {
+            return Err(FeeCapBelowBaseFee {
+                tx_hash: tx.tx_hash,
+                max_fee_per_gas,
+                base_fee_per_gas,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Constraints a batch of blocks must satisfy to be proved in one circuit.
+#[derive(Debug, Clone)]
+pub struct BatchPolicy {
+    /// Rows available to every sub-circuit.
+    pub max_rows: usize,
+    /// Max number of blocks in the batch.
+    pub max_blocks: usize,
+    /// Drop the trailing blocks that do not fit instead of failing.
+    pub auto_truncate: bool,
+    /// Measure the blocks with rayon, one window of as many blocks as threads at a time.
+    pub parallel: bool,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            max_rows: (1 << *DEGREE) - 256,
+            max_blocks: MAX_INNER_BLOCKS,
+            auto_truncate: true,
+            parallel: *PARALLEL_WITNESS,
+        }
+    }
+}
+
+/// Measured row usage of each block of a batch, in the order of the batch.
+/// Every entry is indexed like `SUB_CIRCUIT_NAMES`.
+#[derive(Debug, Clone, Default)]
+pub struct RowUsageReport {
+    pub per_block: Vec<Vec<usize>>,
+}
+
+impl RowUsageReport {
+    /// Measure every block, in parallel if `PARALLEL_WITNESS` is set.
+    pub fn from_block_traces(block_traces: &[BlockTrace]) -> Result<Self, anyhow::Error> {
+        Self::measure(block_traces, *PARALLEL_WITNESS)
+    }
+
+    /// Measure every block. Each measurement is a full witness generation of the block on its
+    /// own, which dominates the cost, so blocks are measured in parallel if `parallel` is set.
+    pub fn measure(block_traces: &[BlockTrace], parallel: bool) -> Result<Self, anyhow::Error> {
+        let per_block = if parallel {
+            block_traces
+                .par_iter()
+                .map(calculate_row_usage_of_trace)
+                .collect::<Result<_, _>>()?
+        } else {
+            block_traces
+                .iter()
+                .map(calculate_row_usage_of_trace)
+                .collect::<Result<_, _>>()?
+        };
+        Ok(Self { per_block })
+    }
+}
+
+/// The batch does not fit into the circuit.
+/// `suggested_split` lists contiguous ranges of block numbers which fit one by one.
+#[derive(Debug, Clone)]
+pub struct CircuitCapacityExceeded {
+    pub rows: usize,
+    pub max_rows: usize,
+    pub suggested_split: Vec<RangeInclusive<u64>>,
+}
+
+impl fmt::Display for CircuitCapacityExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "circuit capacity not enough, rows needed {}, rows available {}, suggested split {:?}",
+            self.rows, self.max_rows, self.suggested_split
+        )
+    }
+}
+
+impl std::error::Error for CircuitCapacityExceeded {}
+
+/// Split the batch into contiguous chunks of blocks, greedily filling each chunk up to the
+/// limits of `policy`. `block_numbers` and `measured.per_block` are aligned with the batch.
+/// A block which exceeds the row budget on its own still gets a chunk of its own.
+pub fn suggest_rechunk(
+    block_numbers: &[u64],
+    measured: &RowUsageReport,
+    policy: &BatchPolicy,
+) -> Vec<RangeInclusive<u64>> {
+    assert_eq!(block_numbers.len(), measured.per_block.len());
+
+    let mut chunks = Vec::new();
+    let mut chunk_start = 0;
+    let mut acc = vec![0; SUB_CIRCUIT_NAMES.len()];
+    for (idx, usage) in measured.per_block.iter().enumerate() {
+        let fits = idx - chunk_start < policy.max_blocks
+            && acc
+                .iter()
+                .zip_eq(usage.iter())
+                .all(|(acc, usage)| acc + usage < policy.max_rows);
+        if !fits && idx > chunk_start {
+            chunks.push(block_numbers[chunk_start]..=block_numbers[idx - 1]);
+            chunk_start = idx;
+            acc.iter_mut().for_each(|acc| *acc = 0);
+        }
+        acc.iter_mut()
+            .zip_eq(usage.iter())
+            .for_each(|(acc, usage)| *acc += usage);
+    }
+    if chunk_start < block_numbers.len() {
+        chunks.push(block_numbers[chunk_start]..=block_numbers[block_numbers.len() - 1]);
+    }
+    chunks
+}
+
+fn block_number_of_trace(block_trace: &BlockTrace) -> u64 {
+    block_trace.header.number.unwrap_or_default().as_u64()
+}
+
 // FIXME: we need better API name for this.
 // This function also mutates the block trace.
 /// ...
 pub fn check_batch_capacity(block_traces: &mut Vec<BlockTrace>) -> Result<(), anyhow::Error> {
+    if !*AUTO_TRUNCATE {
+        if block_traces.len() > MAX_INNER_BLOCKS {
+            bail!("too many blocks");
+        }
+        log::debug!("AUTO_TRUNCATE=false, keep batch as is");
+        return Ok(());
+    }
+    check_batch_capacity_with_policy(block_traces, &BatchPolicy::default())
+}
+
+/// Check the batch against `policy`, truncating it if `policy.auto_truncate` is set.
+/// Fails with [`CircuitCapacityExceeded`] if (part of) the batch does not fit.
+pub fn check_batch_capacity_with_policy(
+    block_traces: &mut Vec<BlockTrace>,
+    policy: &BatchPolicy,
+) -> Result<(), anyhow::Error> {
     let block_traces_len = block_traces.len();
     let total_tx_count = block_traces
         .iter()
@@ -71,58 +373,106 @@ pub fn check_batch_capacity(block_traces: &mut Vec<BlockTrace>) -> Result<(), an
         total_tx_len_sum
     );
 
-    if block_traces_len > MAX_INNER_BLOCKS {
+    if block_traces_len > policy.max_blocks {
         bail!("too many blocks");
     }
 
-    if !*AUTO_TRUNCATE {
-        log::debug!("AUTO_TRUNCATE=false, keep batch as is");
-        return Ok(());
-    }
-
+    // Blocks are measured a window at a time, and accumulated in order: the blocks after the
+    // truncation point are measured at most up to the end of its window, and their errors are
+    // never surfaced.
+    let window = if policy.parallel {
+        rayon::current_num_threads()
+    } else {
+        1
+    };
     let t = Instant::now();
     let mut acc = Vec::new();
+    let mut measured = RowUsageReport::default();
     let mut truncate_idx = block_traces.len();
-    for (idx, block) in block_traces.iter().enumerate() {
-        let usage = calculate_row_usage_of_trace(block)?;
-        if acc.is_empty() {
-            acc = usage;
+    'windows: for (window_idx, blocks) in block_traces.chunks(window).enumerate() {
+        let usages: Vec<_> = if policy.parallel {
+            blocks
+                .par_iter()
+                .map(calculate_row_usage_of_trace)
+                .collect()
         } else {
-            acc.iter_mut().zip(usage.iter()).for_each(|(acc, usage)| {
-                *acc += usage;
-            });
-        }
-        let rows = itertools::max(&acc).unwrap();
-        let rows_and_names: Vec<(_, _)> = SUB_CIRCUIT_NAMES
-            .iter()
-            .zip_eq(acc.iter())
-            .collect::<Vec<(_, _)>>();
-        log::debug!(
-            "row usage after block {}({:?}): {}, {:?}",
-            idx,
-            block.header.number,
-            rows,
-            rows_and_names
-        );
-        if *rows >= (1 << *DEGREE) - 256 {
-            log::warn!("truncate blocks [{}..{})", idx, block_traces_len);
-            truncate_idx = idx;
-            break;
+            blocks.iter().map(calculate_row_usage_of_trace).collect()
+        };
+        for (offset, (block, usage)) in blocks.iter().zip(usages).enumerate() {
+            let idx = window_idx * window + offset;
+            let usage = usage?;
+            if acc.is_empty() {
+                acc = usage.clone();
+            } else {
+                acc.iter_mut().zip(usage.iter()).for_each(|(acc, usage)| {
+                    *acc += usage;
+                });
+            }
+            measured.per_block.push(usage);
+            let rows = itertools::max(&acc).unwrap();
+            let rows_and_names: Vec<(_, _)> = SUB_CIRCUIT_NAMES
+                .iter()
+                .zip_eq(acc.iter())
+                .collect::<Vec<(_, _)>>();
+            log::debug!(
+                "row usage after block {}({:?}): {}, {:?}",
+                idx,
+                block.header.number,
+                rows,
+                rows_and_names
+            );
+            if *rows >= policy.max_rows {
+                if policy.auto_truncate {
+                    log::warn!("truncate blocks [{}..{})", idx, block_traces_len);
+                    truncate_idx = idx;
+                    break 'windows;
+                }
+                return Err(capacity_exceeded(block_traces, measured, *rows, policy)?.into());
+            }
         }
     }
     log::debug!("check_batch_capacity takes {:?}", t.elapsed());
-    block_traces.truncate(truncate_idx);
-    let total_tx_count2 = block_traces
+    let total_tx_count2 = block_traces[..truncate_idx]
         .iter()
         .map(|b| b.transactions.len())
         .sum::<usize>();
     if total_tx_count != 0 && total_tx_count2 == 0 {
         // the circuit cannot even prove the first non-empty block...
-        bail!("circuit capacity not enough");
+        let rows = *itertools::max(&acc).unwrap();
+        return Err(capacity_exceeded(block_traces, measured, rows, policy)?.into());
     }
+    block_traces.truncate(truncate_idx);
     Ok(())
 }
 
+/// Build the capacity error, measuring the blocks not measured yet for the suggested split.
+fn capacity_exceeded(
+    block_traces: &[BlockTrace],
+    mut measured: RowUsageReport,
+    rows: usize,
+    policy: &BatchPolicy,
+) -> Result<CircuitCapacityExceeded, anyhow::Error> {
+    for block in &block_traces[measured.per_block.len()..] {
+        measured
+            .per_block
+            .push(calculate_row_usage_of_trace(block)?);
+    }
+    let block_numbers = block_traces
+        .iter()
+        .map(block_number_of_trace)
+        .collect::<Vec<_>>();
+    let suggested_split = suggest_rechunk(&block_numbers, &measured, policy);
+    log::warn!(
+        "circuit capacity exceeded, suggested split {:?}",
+        suggested_split
+    );
+    Ok(CircuitCapacityExceeded {
+        rows,
+        max_rows: policy.max_rows,
+        suggested_split,
+    })
+}
+
 pub fn block_traces_to_witness_block(
     block_traces: &[BlockTrace],
 ) -> Result<Block<Fr>, anyhow::Error> {
@@ -245,6 +595,21 @@ pub fn block_traces_to_witness_block(
         witness_block.circuits_params
     );
 
+    // every calldata byte of the traces must end up in the calldata table
+    let expected_calldata_len: usize = block_traces
+        .iter()
+        .flat_map(|block_trace| &block_trace.transactions)
+        .map(|tx| tx.data.len())
+        .sum();
+    let calldata_len: usize = witness_block.txs.iter().map(|tx| tx.call_data.len()).sum();
+    if calldata_len != expected_calldata_len {
+        bail!(
+            "internal error: witness has {} calldata bytes, traces have {}",
+            calldata_len,
+            expected_calldata_len
+        );
+    }
+
     block_apply_mpt_state(&mut witness_block, zktrie_state);
     Ok(witness_block)
 }
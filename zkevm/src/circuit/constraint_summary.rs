@@ -0,0 +1,159 @@
+//! Diffable summary of the constraint system of a circuit, for reviewing circuit changes.
+
+use super::TargetCircuit;
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::plonk::{Any, Circuit, ConstraintSystem, Expression};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// The constraint system of a circuit as configured, independent of any witness.
+///
+/// Gates and lookups are sorted, so reordering their definitions doesn't change the summary,
+/// while any change of their expressions does through `expressions_hash`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintSummary {
+    pub circuit: String,
+    pub degree: usize,
+    pub num_advice_columns: usize,
+    pub num_fixed_columns: usize,
+    pub num_instance_columns: usize,
+    pub num_selectors: usize,
+    pub num_challenges: usize,
+    pub gates: Vec<GateSummary>,
+    pub lookups: Vec<LookupSummary>,
+    /// Columns of the permutation argument, as `{type}:{index}`.
+    pub permutation_columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GateSummary {
+    pub name: String,
+    pub constraint_names: Vec<String>,
+    /// Max degree of the constraints of the gate.
+    pub degree: usize,
+    pub expressions_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LookupSummary {
+    pub name: String,
+    pub num_columns: usize,
+    pub input_degree: usize,
+    pub table_degree: usize,
+    pub expressions_hash: String,
+}
+
+/// Render an expression with its queried cells as column type, column index and rotation.
+/// Unlike the debug representation, this leaves out the query indices, which depend on the
+/// order the gates are defined in.
+fn render_expression(expression: &Expression<Fr>) -> String {
+    expression.evaluate(
+        &|constant| format!("{:?}", constant),
+        &|selector| format!("{:?}", selector),
+        &|query| format!("fixed:{}@{}", query.column_index(), query.rotation().0),
+        &|query| format!("advice:{}@{}", query.column_index(), query.rotation().0),
+        &|query| format!("instance:{}@{}", query.column_index(), query.rotation().0),
+        &|challenge| format!("challenge:{}", challenge.index()),
+        &|a| format!("(-{})", a),
+        &|a, b| format!("({} + {})", a, b),
+        &|a, b| format!("({} * {})", a, b),
+        &|a, scalar| format!("({} * {:?})", a, scalar),
+    )
+}
+
+/// Short hash of the rendered expressions, which covers their structure, queried cells and
+/// constants.
+fn expressions_hash<'a>(expressions: impl IntoIterator<Item = &'a Expression<Fr>>) -> String {
+    let rendered: Vec<_> = expressions.into_iter().map(render_expression).collect();
+    hex::encode(&Sha256::digest(rendered.join("\n").as_bytes())[..8])
+}
+
+fn max_degree(expressions: &[Expression<Fr>]) -> usize {
+    expressions
+        .iter()
+        .map(Expression::degree)
+        .max()
+        .unwrap_or(0)
+}
+
+fn column_type_name(column_type: &Any) -> &'static str {
+    match column_type {
+        Any::Advice(_) => "advice",
+        Any::Fixed => "fixed",
+        Any::Instance => "instance",
+    }
+}
+
+/// Configure circuit `C` on an empty constraint system and summarize it.
+pub fn constraint_summary<C: TargetCircuit>() -> ConstraintSummary {
+    let mut cs = ConstraintSystem::<Fr>::default();
+    C::Inner::configure(&mut cs);
+
+    let mut gates: Vec<_> = cs
+        .gates()
+        .iter()
+        .map(|gate| GateSummary {
+            name: gate.name().to_string(),
+            constraint_names: (0..gate.polynomials().len())
+                .map(|i| gate.constraint_name(i).to_string())
+                .collect(),
+            degree: max_degree(gate.polynomials()),
+            expressions_hash: expressions_hash(gate.polynomials()),
+        })
+        .collect();
+    gates.sort();
+
+    let mut lookups: Vec<_> = cs
+        .lookups()
+        .iter()
+        .map(|lookup| LookupSummary {
+            name: lookup.name.to_string(),
+            num_columns: lookup.input_expressions().len(),
+            input_degree: max_degree(lookup.input_expressions()),
+            table_degree: max_degree(lookup.table_expressions()),
+            expressions_hash: expressions_hash(
+                lookup
+                    .input_expressions()
+                    .iter()
+                    .chain(lookup.table_expressions()),
+            ),
+        })
+        .collect();
+    lookups.sort();
+
+    let mut permutation_columns: Vec<_> = cs
+        .permutation()
+        .get_columns()
+        .iter()
+        .map(|column| {
+            format!(
+                "{}:{}",
+                column_type_name(column.column_type()),
+                column.index()
+            )
+        })
+        .collect();
+    permutation_columns.sort();
+
+    ConstraintSummary {
+        circuit: C::name(),
+        degree: cs.degree(),
+        num_advice_columns: cs.num_advice_columns(),
+        num_fixed_columns: cs.num_fixed_columns(),
+        num_instance_columns: cs.num_instance_columns(),
+        num_selectors: cs.num_selectors,
+        num_challenges: cs.num_challenges(),
+        gates,
+        lookups,
+        permutation_columns,
+    }
+}
+
+/// Write the [`constraint_summary`] of circuit `C` to `path` as pretty json.
+pub fn write_constraint_summary<C: TargetCircuit>(path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let mut json = serde_json::to_string_pretty(&constraint_summary::<C>())?;
+    json.push('\n');
+    std::fs::write(path, json)?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use ethers_core::types::U256;
+use std::fmt;
+use types::eth::BlockTrace;
+
+/// Transaction type of L1 messages. Their nonce is the index in the L1 message queue.
+pub const L1_MESSAGE_TX_TYPE: u8 = 0x7E;
+
+/// State of the L1 message queue before a chunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct L1MessageQueueSnapshot {
+    /// Index of the first message not yet included nor skipped by a previous chunk.
+    pub next_queue_index: u64,
+}
+
+/// Which queued L1 messages a chunk includes and which it skips.
+///
+/// Every message between the queue pointer and the last included message which is not
+/// included is skipped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct L1MessageAccounting {
+    /// Queue pointer before the chunk.
+    pub start_queue_index: u64,
+    /// Queue indices of the L1 messages included in the chunk, in order.
+    pub included: Vec<u64>,
+    /// Bit `i` of the bitmap is set if message `start_queue_index + i` is skipped, 256 messages
+    /// per word, as checked by the rollup contract.
+    pub skipped_bitmap: Vec<U256>,
+    /// Queue pointer after the chunk.
+    pub next_queue_index: u64,
+}
+
+/// An L1 message of the traces disagrees with the queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1MessageAccountingError {
+    pub queue_index: u64,
+    pub reason: String,
+}
+
+impl fmt::Display for L1MessageAccountingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid L1 message with queue index {}: {}",
+            self.queue_index, self.reason
+        )
+    }
+}
+
+impl std::error::Error for L1MessageAccountingError {}
+
+impl L1MessageAccounting {
+    pub fn from_traces(
+        block_traces: &[BlockTrace],
+        queue_snapshot: L1MessageQueueSnapshot,
+    ) -> Result<Self, L1MessageAccountingError> {
+        let start_queue_index = queue_snapshot.next_queue_index;
+        let mut next_queue_index = start_queue_index;
+        let mut included = vec![];
+        let mut skipped = vec![];
+
+        let l1_messages = block_traces
+            .iter()
+            .flat_map(|block_trace| &block_trace.transactions)
+            .filter(|tx| tx.type_ == L1_MESSAGE_TX_TYPE);
+        for tx in l1_messages {
+            let queue_index = tx.nonce;
+            if queue_index < start_queue_index {
+                return Err(L1MessageAccountingError {
+                    queue_index,
+                    reason: format!(
+                        "already consumed, queue pointer is at {}",
+                        start_queue_index
+                    ),
+                });
+            }
+            if queue_index < next_queue_index {
+                return Err(L1MessageAccountingError {
+                    queue_index,
+                    reason: format!(
+                        "out of order, expected queue index {} or above",
+                        next_queue_index
+                    ),
+                });
+            }
+            skipped.extend(next_queue_index..queue_index);
+            included.push(queue_index);
+            next_queue_index = queue_index + 1;
+        }
+
+        let num_popped = (next_queue_index - start_queue_index) as usize;
+        let mut skipped_bitmap = vec![U256::zero(); (num_popped + 255) / 256];
+        for queue_index in skipped {
+            let offset = (queue_index - start_queue_index) as usize;
+            skipped_bitmap[offset / 256] |= U256::one() << (offset % 256);
+        }
+
+        Ok(Self {
+            start_queue_index,
+            included,
+            skipped_bitmap,
+            next_queue_index,
+        })
+    }
+
+    /// Number of messages included or skipped by the chunk.
+    pub fn num_popped(&self) -> u64 {
+        self.next_queue_index - self.start_queue_index
+    }
+
+    pub fn is_skipped(&self, queue_index: u64) -> bool {
+        if queue_index < self.start_queue_index || queue_index >= self.next_queue_index {
+            return false;
+        }
+        let offset = (queue_index - self.start_queue_index) as usize;
+        self.skipped_bitmap[offset / 256].bit(offset % 256)
+    }
+
+    /// The skip bitmap as big-endian 32 bytes words, the layout of the contract calldata.
+    pub fn skipped_bitmap_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; 32 * self.skipped_bitmap.len()];
+        for (word, chunk) in self.skipped_bitmap.iter().zip(bytes.chunks_mut(32)) {
+            word.to_big_endian(chunk);
+        }
+        bytes
+    }
+}
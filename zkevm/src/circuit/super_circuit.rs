@@ -1,6 +1,11 @@
-use super::{TargetCircuit, DEGREE};
+use super::{
+    log2_ceil, CircuitDescription, PublicInputField, TargetCircuit, DEGREE, SUB_CIRCUIT_NAMES,
+};
 
-use super::{MAX_CALLDATA, MAX_INNER_BLOCKS, MAX_TXS};
+use super::{
+    MAX_BYTECODE, MAX_CALLDATA, MAX_EXP_STEPS, MAX_INNER_BLOCKS, MAX_KECCAK_ROWS, MAX_MPT_ROWS,
+    MAX_RWS, MAX_TXS,
+};
 use anyhow::bail;
 use halo2_proofs::halo2curves::bn256::Fr;
 use zkevm_circuits::util::SubCircuit;
@@ -8,6 +13,27 @@ use zkevm_circuits::{super_circuit::SuperCircuit as SuperCircuitTpl, witness};
 
 type SuperCircuitImpl = SuperCircuitTpl<Fr, MAX_TXS, MAX_CALLDATA, MAX_INNER_BLOCKS, 0x1000>;
 
+/// Rows of the largest sub-circuit budget, plus the rows kept free for blinding.
+const MAX_ROWS: usize = {
+    let budgets = [
+        MAX_RWS,
+        MAX_CALLDATA,
+        MAX_BYTECODE,
+        MAX_MPT_ROWS,
+        MAX_KECCAK_ROWS,
+        MAX_EXP_STEPS,
+    ];
+    let mut max = 0;
+    let mut i = 0;
+    while i < budgets.len() {
+        if budgets[i] > max {
+            max = budgets[i];
+        }
+        i += 1;
+    }
+    max + 256
+};
+
 pub struct SuperCircuit {}
 
 impl TargetCircuit for SuperCircuit {
@@ -41,4 +67,23 @@ impl TargetCircuit for SuperCircuit {
     fn public_input_len() -> usize {
         1
     }
+
+    fn required_params_degree() -> u32 {
+        const REQUIRED_PARAMS_DEGREE: u32 = log2_ceil(MAX_ROWS);
+        REQUIRED_PARAMS_DEGREE
+    }
+
+    fn describe() -> CircuitDescription {
+        CircuitDescription {
+            name: Self::name(),
+            version: env!("CARGO_PKG_VERSION"),
+            degree: *DEGREE as u32,
+            sub_circuits: SUB_CIRCUIT_NAMES.to_vec(),
+            supported_eip_features: vec!["EIP-155", "EIP-1559", "EIP-2718", "EIP-2930"],
+            public_inputs: vec![PublicInputField {
+                name: "pi_hash",
+                len: Self::public_input_len(),
+            }],
+        }
+    }
 }
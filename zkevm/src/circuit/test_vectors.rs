@@ -0,0 +1,188 @@
+//! Reference witness assignments of a circuit, to be compared against when changing circuits.
+//! The assignments of a circuit like [`super::SuperCircuit`] are too large to commit, so the
+//! fixtures are their [`TestVectorDigests`].
+
+use super::{block_traces_to_witness_block, TargetCircuit, DEGREE};
+use halo2_proofs::dev::{CellValue, MockProver};
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use serde::{Deserializer, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use types::eth::BlockTrace;
+
+/// The assigned advice and instance cells of each used row, keyed by column, e.g. `advice_3` or
+/// `instance_0`. Fixed columns, which hold the lookup tables, are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    #[serde(with = "fr_rows")]
+    pub rows: Vec<HashMap<String, Fr>>,
+}
+
+/// The sha256 of the assigned cells of each column of [`TestVectors`], as `(row, value)` pairs in
+/// row order with the row as a little-endian `u64`. One digest per column keeps the fixture of
+/// any circuit small; [`verify_against_test_vectors`] on the full vectors tells the cells of a
+/// changed column.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectorDigests {
+    pub columns: BTreeMap<String, String>,
+}
+
+impl TestVectors {
+    pub fn digests(&self) -> TestVectorDigests {
+        let mut hashers: BTreeMap<&str, Sha256> = BTreeMap::new();
+        for (row, cells) in self.rows.iter().enumerate() {
+            for (column, value) in cells {
+                let hasher = hashers.entry(column.as_str()).or_default();
+                hasher.update((row as u64).to_le_bytes());
+                hasher.update(value.to_repr());
+            }
+        }
+        TestVectorDigests {
+            columns: hashers
+                .into_iter()
+                .map(|(column, hasher)| (column.to_string(), hex::encode(hasher.finalize())))
+                .collect(),
+        }
+    }
+}
+
+/// A cell which differs between computed and reference test vectors. `None` is an unassigned
+/// cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub row: usize,
+    pub column: String,
+    pub computed: Option<Fr>,
+    pub reference: Option<Fr>,
+}
+
+/// Values as little-endian hex, with the columns of a row sorted so the json is stable.
+mod fr_rows {
+    use super::*;
+    use serde::de::Error;
+    use serde::{Deserialize, Serialize};
+
+    pub fn serialize<S: Serializer>(
+        rows: &[HashMap<String, Fr>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .map(|(column, value)| (column, hex::encode(value.to_repr())))
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<HashMap<String, Fr>>, D::Error> {
+        let rows = Vec::<HashMap<String, String>>::deserialize(deserializer)?;
+        rows.into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(column, value)| {
+                        let repr: [u8; 32] = hex::decode(&value)
+                            .map_err(D::Error::custom)?
+                            .try_into()
+                            .map_err(|_| D::Error::custom("field element is not 32 bytes"))?;
+                        Option::from(Fr::from_repr(repr))
+                            .map(|value| (column, value))
+                            .ok_or_else(|| D::Error::custom("non canonical field element"))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn record_columns(
+    rows: &mut Vec<HashMap<String, Fr>>,
+    prefix: &str,
+    columns: &[Vec<CellValue<Fr>>],
+    used_rows: usize,
+) {
+    for (index, column) in columns.iter().enumerate() {
+        for (row, cell) in column.iter().take(used_rows).enumerate() {
+            if let CellValue::Assigned(value) = cell {
+                if rows.len() <= row {
+                    rows.resize_with(row + 1, HashMap::new);
+                }
+                rows[row].insert(format!("{}_{}", prefix, index), *value);
+            }
+        }
+    }
+}
+
+/// Synthesize circuit `C` for the traces and record its assigned advice and instance cells in
+/// the rows the circuit uses, see [`TargetCircuit::estimate_rows_from_witness_block`]. All
+/// rows are recorded for circuits which don't estimate their rows.
+pub fn generate_test_vectors<C: TargetCircuit>(
+    block_traces: &[BlockTrace],
+) -> anyhow::Result<TestVectors> {
+    let witness_block = block_traces_to_witness_block(block_traces)?;
+    let (circuit, instance) = C::from_witness_block(&witness_block)?;
+    let prover = MockProver::<Fr>::run(*DEGREE as u32, &circuit, instance.clone())?;
+    let used_rows = match C::estimate_rows_from_witness_block(&witness_block) {
+        0 => usize::MAX,
+        rows => rows,
+    };
+
+    let mut rows = Vec::new();
+    record_columns(&mut rows, "advice", prover.advice(), used_rows);
+    let instance: Vec<Vec<_>> = instance
+        .into_iter()
+        .map(|column| column.into_iter().map(CellValue::Assigned).collect())
+        .collect();
+    record_columns(&mut rows, "instance", &instance, used_rows);
+    Ok(TestVectors { rows })
+}
+
+/// Every cell which is assigned differently, or only on one side, in row then column order.
+pub fn verify_against_test_vectors(
+    computed: &TestVectors,
+    reference: &TestVectors,
+) -> Vec<Mismatch> {
+    let num_rows = computed.rows.len().max(reference.rows.len());
+    let empty = HashMap::new();
+    let mut mismatches = Vec::new();
+    for row in 0..num_rows {
+        let computed_row = computed.rows.get(row).unwrap_or(&empty);
+        let reference_row = reference.rows.get(row).unwrap_or(&empty);
+        let columns: BTreeSet<_> = computed_row.keys().chain(reference_row.keys()).collect();
+        for column in columns {
+            let computed = computed_row.get(column).copied();
+            let reference = reference_row.get(column).copied();
+            if computed != reference {
+                mismatches.push(Mismatch {
+                    row,
+                    column: column.clone(),
+                    computed,
+                    reference,
+                });
+            }
+        }
+    }
+    mismatches
+}
+
+/// The columns whose digests differ, or which are only on one side, in column order.
+pub fn verify_against_test_vector_digests(
+    computed: &TestVectorDigests,
+    reference: &TestVectorDigests,
+) -> Vec<String> {
+    let columns: BTreeSet<_> = computed
+        .columns
+        .keys()
+        .chain(reference.columns.keys())
+        .collect();
+    columns
+        .into_iter()
+        .filter(|column| computed.columns.get(*column) != reference.columns.get(*column))
+        .cloned()
+        .collect()
+}
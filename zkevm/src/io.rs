@@ -1,8 +1,11 @@
 use std::{
     io::{Cursor, Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+use crate::attest::GIT_VERSION;
+use crate::utils::DEFAULT_SERDE_FORMAT;
+
 use halo2_proofs::{
     halo2curves::bn256::{Bn256, Fq, Fr, G1Affine},
     plonk::VerifyingKey,
@@ -10,6 +13,7 @@ use halo2_proofs::{
     SerdeFormat,
 };
 use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
 use snark_verifier::util::arithmetic::PrimeField;
 
 pub fn serialize_fr(f: &Fr) -> Vec<u8> {
@@ -127,6 +131,75 @@ pub fn serialize_vk(vk: &VerifyingKey<G1Affine>) -> Vec<u8> {
     result
 }
 
+/// Short hex fingerprint of serialized vk bytes, used to identify circuit versions.
+pub fn vk_fingerprint(vk: &[u8]) -> String {
+    hex::encode(&Sha256::digest(vk)[..8])
+}
+
+const VK_PEM_BEGIN: &str = "-----BEGIN SCROLL CIRCUIT VK-----";
+const VK_PEM_END: &str = "-----END SCROLL CIRCUIT VK-----";
+const VK_PEM_LINE_LEN: usize = 64;
+
+/// Armor serialized vk bytes as base64 between PEM-like markers, for easy sharing as text.
+pub fn vk_to_pem(vk_bytes: &[u8]) -> String {
+    let encoded = base64::encode(vk_bytes);
+    let mut pem = String::with_capacity(encoded.len() + encoded.len() / VK_PEM_LINE_LEN + 80);
+    pem.push_str(VK_PEM_BEGIN);
+    pem.push('\n');
+    for line in encoded.as_bytes().chunks(VK_PEM_LINE_LEN) {
+        // base64 output is ascii
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(VK_PEM_END);
+    pem.push('\n');
+    pem
+}
+
+/// Read vk bytes armored by [`vk_to_pem`].
+pub fn pem_to_vk(pem: &str) -> anyhow::Result<Vec<u8>> {
+    let pem = pem.trim();
+    let body = pem
+        .strip_prefix(VK_PEM_BEGIN)
+        .and_then(|pem| pem.strip_suffix(VK_PEM_END))
+        .ok_or_else(|| anyhow::anyhow!("missing {} / {} markers", VK_PEM_BEGIN, VK_PEM_END))?;
+    let encoded: String = body.split_whitespace().collect();
+    Ok(base64::decode(encoded)?)
+}
+
+/// Forwards proof bytes to a sink, e.g. an object storage upload, as soon as the prover writes
+/// them to the transcript. Nothing is buffered here, buffering is up to the sink.
+pub struct StreamingProofUploader {
+    sink: Box<dyn Write + Send>,
+    bytes_uploaded: usize,
+}
+
+impl StreamingProofUploader {
+    pub fn new(sink: impl Write + Send + 'static) -> Self {
+        Self {
+            sink: Box::new(sink),
+            bytes_uploaded: 0,
+        }
+    }
+
+    /// Number of proof bytes written to the sink so far.
+    pub fn bytes_uploaded(&self) -> usize {
+        self.bytes_uploaded
+    }
+}
+
+impl Write for StreamingProofUploader {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.sink.write(buf)?;
+        self.bytes_uploaded += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.sink.flush()
+    }
+}
+
 pub fn write_verify_circuit_vk(folder: &mut PathBuf, verify_circuit_vk: &[u8]) {
     folder.push("verify_circuit.vkey");
     let mut fd = std::fs::File::create(folder.as_path()).unwrap();
@@ -224,3 +297,119 @@ pub fn load_instances_flat(buf: &[u8]) -> Vec<Vec<Vec<Fr>>> {
 
     vec![vec![ret]]
 }
+
+/// Everything a target circuit proof was generated from, written next to the proof file.
+#[derive(Debug, Clone, PartialEq, Eq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub struct ProofManifest {
+    pub circuit: String,
+    pub block_numbers: Vec<u64>,
+    /// sha256 of the target circuit params, as serialized in the params file.
+    pub params_sha256: String,
+    pub vk_fingerprint: String,
+    pub prover_version: String,
+    /// RFC 3339 time the manifest was written.
+    pub timestamp: String,
+}
+
+/// The manifest does not describe the proof it is next to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestMismatch {
+    pub field: &'static str,
+    pub manifest: String,
+    pub proof: String,
+}
+
+impl std::fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "proof manifest mismatch on {}: manifest {}, proof {}",
+            self.field, self.manifest, self.proof
+        )
+    }
+}
+
+impl std::error::Error for ManifestMismatch {}
+
+impl ProofManifest {
+    /// Check the fields the proof itself carries: circuit, block numbers and vk.
+    pub fn validate(
+        &self,
+        circuit: &str,
+        block_numbers: &[u64],
+        vk: &[u8],
+    ) -> Result<(), ManifestMismatch> {
+        let fields = [
+            ("circuit", self.circuit.clone(), circuit.to_string()),
+            (
+                "block_numbers",
+                format!("{:?}", self.block_numbers),
+                format!("{:?}", block_numbers),
+            ),
+            (
+                "vk_fingerprint",
+                self.vk_fingerprint.clone(),
+                vk_fingerprint(vk),
+            ),
+        ];
+        for (field, manifest, proof) in fields {
+            if manifest != proof {
+                return Err(ManifestMismatch {
+                    field,
+                    manifest,
+                    proof,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Path of the manifest of a proof file, i.e. `<proof file>.manifest.json`.
+pub fn manifest_path(proof_path: &Path) -> PathBuf {
+    let mut path = proof_path.as_os_str().to_owned();
+    path.push(".manifest.json");
+    PathBuf::from(path)
+}
+
+/// sha256 of `params`, as serialized in a params file.
+pub fn params_sha256(params: &ParamsKZG<Bn256>) -> String {
+    let mut params_hasher = Sha256::new();
+    params
+        .write_custom(&mut params_hasher, DEFAULT_SERDE_FORMAT)
+        .unwrap();
+    hex::encode(params_hasher.finalize())
+}
+
+/// Write the manifest of a proof of `circuit` for `block_numbers` with verifying key `vk`, made
+/// with the params of sha256 `params_sha256`, next to the proof file at `path`.
+pub fn write_proof_manifest(
+    circuit: &str,
+    block_numbers: &[u64],
+    vk: &[u8],
+    params_sha256: &str,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let manifest = ProofManifest {
+        circuit: circuit.to_string(),
+        block_numbers: block_numbers.to_vec(),
+        params_sha256: params_sha256.to_string(),
+        vk_fingerprint: vk_fingerprint(vk),
+        prover_version: format!("{}-{}", env!("CARGO_PKG_VERSION"), GIT_VERSION),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+    };
+    let fd = std::fs::File::create(manifest_path(path))?;
+    serde_json::to_writer_pretty(fd, &manifest)?;
+    Ok(())
+}
+
+/// Read the manifest next to the proof file at `path`, if there is one.
+pub fn read_proof_manifest(path: &Path) -> anyhow::Result<Option<ProofManifest>> {
+    let manifest_path = manifest_path(path);
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&std::fs::read(
+        manifest_path,
+    )?)?))
+}
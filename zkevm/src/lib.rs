@@ -1,3 +1,4 @@
+pub mod attest;
 pub mod circuit;
 pub mod io;
 pub mod prover;
@@ -1,37 +1,60 @@
 use crate::utils::read_env_var;
+use eth_types::H256;
 use halo2_proofs::halo2curves::bn256::{Bn256, G1Affine};
 use halo2_proofs::plonk::ProvingKey;
 use halo2_proofs::poly::kzg::commitment::ParamsKZG;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use rand_xorshift::XorShiftRng;
 use std::collections::HashMap;
 
 mod evm;
+mod incremental;
 mod inner_circuit;
 mod inner_proof;
 mod mock;
+mod options;
 mod outer_circuit;
 mod outer_proof;
+mod proof_system;
+mod redundant;
+mod streaming;
 mod util;
 
+pub use incremental::StateRootMismatch;
 pub use inner_proof::TargetCircuitProof;
+pub use mock::{ConstraintViolation, ConstraintViolationReport, MockProveError};
+pub use options::{check_config_hashes, ConfigHashMismatch, ProverOptions};
 pub use outer_proof::AggCircuitProof;
+pub use proof_system::{Halo2PlonkSystem, ProofSystem};
+pub use redundant::{check_redundant_proofs, RedundancyMismatch, RedundantProofResult, Seed};
 
 #[cfg(target_os = "linux")]
 extern crate procfs;
 
 pub static OPT_MEM: Lazy<bool> = Lazy::new(|| read_env_var("OPT_MEM", false));
 pub static MOCK_PROVE: Lazy<bool> = Lazy::new(|| read_env_var("MOCK_PROVE", false));
+/// Write a `ProofManifest` next to each target circuit proof dumped by the prover.
+pub static WRITE_MANIFEST: Lazy<bool> = Lazy::new(|| read_env_var("WRITE_MANIFEST", false));
 
 #[derive(Debug)]
 /// This is the aggregation prover that takes in a list of traces, produces
 /// a proof that can be verified on chain.
-pub struct Prover {
+///
+/// The target circuits are set up and proved by the proof system `S`, see [`ProofSystem`] for
+/// which APIs take which backends.
+pub struct Prover<S: ProofSystem = Halo2PlonkSystem> {
     pub params: ParamsKZG<Bn256>,
+    /// sha256 of `params`, hashed on first use since the params are large.
+    params_sha256: OnceCell<String>,
     pub agg_params: ParamsKZG<Bn256>,
     pub rng: XorShiftRng,
     /// We may have a list of public keys for different inner circuits.
     /// Those keys are stored as a hash map, and keyed by a `name` String.
-    pub target_circuit_pks: HashMap<String, ProvingKey<G1Affine>>,
+    pub target_circuit_pks: HashMap<String, S::ProvingKey>,
     pub agg_pk: Option<ProvingKey<G1Affine>>,
+    pub options: ProverOptions,
+    /// State root after the last block proved incrementally.
+    running_state_root: Option<H256>,
+    last_mock_prove_report: Option<ConstraintViolationReport>,
+    pub system: S,
 }
@@ -0,0 +1,70 @@
+//! Prove blocks one at a time, chaining their state roots
+
+use super::{Prover, TargetCircuitProof};
+use crate::circuit::TargetCircuit;
+use eth_types::H256;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use std::fmt;
+use types::eth::BlockTrace;
+
+/// A block does not start from the state root the previously proved block ended at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateRootMismatch {
+    pub block: u64,
+    pub expected: H256,
+    pub found: H256,
+}
+
+impl fmt::Display for StateRootMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} starts from state root {:?}, expected {:?}",
+            self.block, self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for StateRootMismatch {}
+
+impl Prover {
+    /// Prove a single block which must start from the state root the previous block proved by
+    /// this method ended at. The previous state root is part of the public inputs of the
+    /// circuit, so the chain of proofs is the chain of state transitions. The rng is derived
+    /// from the prover's.
+    ///
+    /// The first block sets the running state root, unless it was set with
+    /// [`Self::set_running_state_root`].
+    pub fn create_proof_incremental<C: TargetCircuit>(
+        &mut self,
+        block_trace: BlockTrace,
+    ) -> anyhow::Result<TargetCircuitProof> {
+        let root_before = block_trace.storage_trace.root_before;
+        if let Some(expected) = self.running_state_root {
+            if root_before != expected {
+                return Err(StateRootMismatch {
+                    block: block_trace.header.number.unwrap_or_default().as_u64(),
+                    expected,
+                    found: root_before,
+                }
+                .into());
+            }
+        }
+
+        let mut rng = XorShiftRng::from_rng(&mut self.rng)?;
+        let proof = self.create_target_circuit_proof::<C>(&block_trace, &mut rng)?;
+        self.running_state_root = Some(block_trace.storage_trace.root_after);
+        Ok(proof)
+    }
+
+    /// State root after the last block proved by [`Self::create_proof_incremental`].
+    pub fn running_state_root(&self) -> Option<H256> {
+        self.running_state_root
+    }
+
+    /// Start the next incremental proof from `root`, or from any root if `None`.
+    pub fn set_running_state_root(&mut self, root: Option<H256>) {
+        self.running_state_root = root;
+    }
+}
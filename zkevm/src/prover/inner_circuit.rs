@@ -1,19 +1,36 @@
 //! Inner circuit related APIs
 
-use super::{Prover, TargetCircuitProof};
-use crate::circuit::{block_traces_to_witness_block, check_batch_capacity, TargetCircuit, DEGREE};
-use crate::io::{serialize_instance, serialize_vk};
-use crate::prover::MOCK_PROVE;
+use super::{ProofSystem, Prover, TargetCircuitProof};
+use crate::circuit::{
+    block_traces_to_witness_block, check_batch_capacity, check_calldata_size, check_fee_caps,
+    check_gas_accounting, suggest_rechunk, BatchGasUsage, BatchPolicy, RowUsageReport,
+    TargetCircuit,
+};
+use crate::io::{serialize_instance, serialize_vk, write_proof_manifest};
+use crate::prover::{MOCK_PROVE, WRITE_MANIFEST};
 use crate::utils::metric_of_witness_block;
 use anyhow::{bail, Error};
-use halo2_proofs::dev::MockProver;
-use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2_proofs::plonk::{ProvingKey, VerifyingKey};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
 use log::info;
 use rand::Rng;
-use snark_verifier_sdk::halo2::gen_snark_shplonk;
+use snark_verifier_sdk::Snark;
+use std::ops::RangeInclusive;
+use std::path::Path;
 use types::eth::BlockTrace;
 
-impl Prover {
+/// The target circuit proofs are halo2 SNARKs, made by any proof system with the types of
+/// [`super::Halo2PlonkSystem`].
+impl<S> Prover<S>
+where
+    S: ProofSystem<
+        Params = ParamsKZG<Bn256>,
+        ProvingKey = ProvingKey<G1Affine>,
+        VerifyingKey = VerifyingKey<G1Affine>,
+        Proof = Snark,
+    >,
+{
     /// Input a list of traces, generate an instance for the outer circuit.
     ///
     pub fn prove_inner_circuit<C: TargetCircuit>(
@@ -41,24 +58,8 @@ impl Prover {
         rng: &mut (impl Rng + Send),
     ) -> anyhow::Result<TargetCircuitProof, Error> {
         let total_num_of_blocks = block_traces.len();
-
-        //
-        // Process the traces and prepare the witnesses and inputs to the inner circuits
-        //
-        let ((circuit, instance), num_of_proved_blocks) = {
-            let mut block_traces = block_traces.to_vec();
-            check_batch_capacity(&mut block_traces)?;
-            let witness_block = block_traces_to_witness_block(&block_traces)?;
-            log::info!(
-                "proving batch of len {}, batch metric {:?}",
-                total_num_of_blocks,
-                metric_of_witness_block(&witness_block)
-            );
-            (
-                C::from_witness_block(&witness_block)?,
-                witness_block.context.ctxs.len(),
-            )
-        };
+        let ((circuit, instance), num_of_proved_blocks) =
+            target_circuit_from_traces::<C>(block_traces)?;
 
         //
         // generate the proof for the inner circuit
@@ -70,13 +71,81 @@ impl Prover {
             block_traces[block_traces.len() - 1].header.hash.unwrap(),
             block_traces.len()
         );
-        self.create_target_circuit_proof_from_circuit::<C>(
+        let mut proof = self.create_target_circuit_proof_from_circuit::<C>(
             circuit,
             instance,
             rng,
             total_num_of_blocks,
             num_of_proved_blocks,
-        )
+        )?;
+        proof.block_numbers = block_traces[..num_of_proved_blocks]
+            .iter()
+            .map(|block_trace| block_trace.header.number.unwrap_or_default().as_u64())
+            .collect();
+        Ok(proof)
+    }
+
+    /// Write `proof` to `file_path`, and its manifest next to it if `WRITE_MANIFEST` is set.
+    pub fn dump_target_circuit_proof(
+        &self,
+        proof: &TargetCircuitProof,
+        file_path: &str,
+    ) -> anyhow::Result<()> {
+        proof.dump_to_file(file_path)?;
+        if *WRITE_MANIFEST {
+            write_proof_manifest(
+                &proof.name,
+                &proof.block_numbers,
+                &proof.vk,
+                self.params_sha256(),
+                Path::new(file_path),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Copy `proof` with some public inputs replaced, to check that the circuit enforces them.
+    /// Each override is an index into the instance values flattened column by column, and its
+    /// new value.
+    ///
+    /// The proof bytes are kept as they are: halo2 can't redo part of the proving without the
+    /// witness. Since the instances are committed to in the transcript, any effective override
+    /// makes the proof fail verification.
+    pub fn create_proof_with_instance_override<C: TargetCircuit>(
+        &self,
+        proof: &TargetCircuitProof,
+        overrides: &[(usize, Fr)],
+    ) -> anyhow::Result<TargetCircuitProof> {
+        if proof.name != C::name() {
+            bail!("proof of circuit {}, expected {}", proof.name, C::name());
+        }
+        let mut snark = proof.snark.clone();
+        let num_instances: usize = snark.instances.iter().map(Vec::len).sum();
+        for &(index, value) in overrides {
+            let value_ref = snark
+                .instances
+                .iter_mut()
+                .flatten()
+                .nth(index)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "instance index {} out of range, proof has {} instances",
+                        index,
+                        num_instances
+                    )
+                })?;
+            *value_ref = value;
+        }
+
+        Ok(TargetCircuitProof {
+            name: proof.name.clone(),
+            snark,
+            vk: proof.vk.clone(),
+            total_num_of_blocks: proof.total_num_of_blocks,
+            num_of_proved_blocks: proof.num_of_proved_blocks,
+            block_numbers: proof.block_numbers.clone(),
+            config_hash: proof.config_hash.clone(),
+        })
     }
 
     ///
@@ -91,25 +160,12 @@ impl Prover {
         num_of_proved_blocks: usize,
     ) -> anyhow::Result<TargetCircuitProof, Error> {
         if *MOCK_PROVE {
-            log::info!("mock prove {} start", C::name());
-            let prover = MockProver::<Fr>::run(*DEGREE as u32, &circuit, instance.clone())?;
-            if let Err(errs) = prover.verify_par() {
-                log::error!("err num: {}", errs.len());
-                for err in &errs {
-                    log::error!("{}", err);
-                }
-                bail!("{:#?}", errs);
-            }
-            log::info!("mock prove {} done", C::name());
-        }
-
-        if !self.target_circuit_pks.contains_key(&C::name()) {
-            self.init_pk::<C>(&C::dummy_inner_circuit());
+            self.mock_prove_circuit::<C>(&circuit, instance.clone())?;
         }
-        let pk = &self.target_circuit_pks[&C::name()];
 
         // Generate the SNARK proof for the inner circuit
-        let snark_proof = gen_snark_shplonk(&self.params, pk, circuit, rng, None::<String>);
+        let snark_proof = self.prove_with_system::<C>(circuit, rng)?;
+        let pk = &self.target_circuit_pks[&C::name()];
 
         let instance_bytes = serialize_instance(&instance);
         let name = C::name();
@@ -125,8 +181,64 @@ impl Prover {
             vk: serialize_vk(pk.get_vk()),
             total_num_of_blocks,
             num_of_proved_blocks,
+            block_numbers: vec![],
+            config_hash: self.options.config_hash_hex(),
         };
 
         Ok(target_proof)
     }
 }
+
+impl Prover {
+    /// Suggest how to split a batch into contiguous chunks of block numbers which fit into the
+    /// circuit, given the measured row usage of each block of the batch.
+    pub fn suggest_rechunk(
+        block_traces: &[BlockTrace],
+        measured: &RowUsageReport,
+    ) -> anyhow::Result<Vec<RangeInclusive<u64>>> {
+        if block_traces.len() != measured.per_block.len() {
+            bail!(
+                "row usage measured for {} blocks, but batch has {} blocks",
+                measured.per_block.len(),
+                block_traces.len()
+            );
+        }
+        let block_numbers = block_traces
+            .iter()
+            .map(|block_trace| block_trace.header.number.unwrap_or_default().as_u64())
+            .collect::<Vec<_>>();
+        Ok(suggest_rechunk(
+            &block_numbers,
+            measured,
+            &BatchPolicy::default(),
+        ))
+    }
+}
+
+/// Process the traces and prepare the witnesses and inputs to the inner circuit. Also returns
+/// the number of blocks the circuit proves.
+pub(super) fn target_circuit_from_traces<C: TargetCircuit>(
+    block_traces: &[BlockTrace],
+) -> anyhow::Result<((C::Inner, Vec<Vec<Fr>>), usize)> {
+    let total_num_of_blocks = block_traces.len();
+    check_gas_accounting(block_traces)?;
+    check_calldata_size(block_traces)?;
+    check_fee_caps(block_traces)?;
+
+    let mut block_traces = block_traces.to_vec();
+    check_batch_capacity(&mut block_traces)?;
+    log::info!(
+        "total gas used of batch: {}",
+        BatchGasUsage::from_block_traces(&block_traces).total_gas_used()
+    );
+    let witness_block = block_traces_to_witness_block(&block_traces)?;
+    log::info!(
+        "proving batch of len {}, batch metric {:?}",
+        total_num_of_blocks,
+        metric_of_witness_block(&witness_block)
+    );
+    Ok((
+        C::from_witness_block(&witness_block)?,
+        witness_block.context.ctxs.len(),
+    ))
+}
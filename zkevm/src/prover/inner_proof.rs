@@ -1,3 +1,4 @@
+use crate::io::{read_proof_manifest, ProofManifest};
 use anyhow::Result;
 use serde_derive::{Deserialize, Serialize};
 use snark_verifier_sdk::Snark;
@@ -13,6 +14,12 @@ pub struct TargetCircuitProof {
     pub vk: Vec<u8>,
     pub num_of_proved_blocks: usize,
     pub total_num_of_blocks: usize,
+    /// Numbers of the proved blocks, empty for proofs not made from block traces.
+    #[serde(default)]
+    pub block_numbers: Vec<u64>,
+    /// Hex of the prover's `ProverOptions::config_hash`, empty for older proofs.
+    #[serde(default)]
+    pub config_hash: String,
 }
 
 impl TargetCircuitProof {
@@ -32,4 +39,19 @@ impl TargetCircuitProof {
         let fd = File::open(file_path)?;
         Ok(Some(serde_json::from_reader(fd)?))
     }
+
+    /// Like [`Self::restore_from_file`], also reading the manifest next to the proof file if
+    /// there is one. A manifest which does not describe the proof is a
+    /// [`crate::io::ManifestMismatch`] error.
+    pub fn restore_with_manifest(file_path: &str) -> Result<Option<(Self, Option<ProofManifest>)>> {
+        let proof = match Self::restore_from_file(file_path)? {
+            Some(proof) => proof,
+            None => return Ok(None),
+        };
+        let manifest = read_proof_manifest(Path::new(file_path))?;
+        if let Some(manifest) = &manifest {
+            manifest.validate(&proof.name, &proof.block_numbers, &proof.vk)?;
+        }
+        Ok(Some((proof, manifest)))
+    }
 }
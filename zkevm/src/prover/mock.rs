@@ -1,21 +1,117 @@
-use super::Prover;
-use crate::circuit::{block_traces_to_witness_block, check_batch_capacity, TargetCircuit, DEGREE};
+use super::{ProofSystem, Prover};
+use crate::circuit::{
+    block_traces_to_witness_block, check_batch_capacity, check_calldata_size, check_fee_caps,
+    check_gas_accounting, TargetCircuit, DEGREE,
+};
 use crate::utils::metric_of_witness_block;
-use anyhow::bail;
-use halo2_proofs::dev::MockProver;
+use halo2_proofs::dev::{MockProver, VerifyFailure};
 use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::poly::commitment::Params;
+use std::fmt;
 use types::eth::BlockTrace;
 
+/// A constraint of the circuit which is not satisfied by the witness.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolation {
+    /// The kind of the failure, e.g. "Lookup" or "ConstraintNotSatisfied".
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl From<&VerifyFailure> for ConstraintViolation {
+    fn from(failure: &VerifyFailure) -> Self {
+        let kind = match failure {
+            VerifyFailure::CellNotAssigned { .. } => "CellNotAssigned",
+            VerifyFailure::ConstraintNotSatisfied { .. } => "ConstraintNotSatisfied",
+            VerifyFailure::ConstraintPoisoned { .. } => "ConstraintPoisoned",
+            VerifyFailure::Lookup { .. } => "Lookup",
+            VerifyFailure::Permutation { .. } => "Permutation",
+        };
+        Self {
+            kind,
+            message: failure.to_string(),
+        }
+    }
+}
+
+/// All the constraint violations found by a mock prove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolationReport {
+    pub circuit: String,
+    pub violations: Vec<ConstraintViolation>,
+}
+
+/// The witness does not satisfy the circuit.
+#[derive(Debug, Clone)]
+pub struct MockProveError {
+    pub total_violations: usize,
+    pub first_violation: ConstraintViolation,
+}
+
+impl fmt::Display for MockProveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mock prove failed with {} constraint violations, first: {}",
+            self.total_violations, self.first_violation.message
+        )
+    }
+}
+
+impl std::error::Error for MockProveError {}
+
+/// Run the mock prover and collect the violations.
+fn run_mock_prover<C: TargetCircuit>(
+    k: u32,
+    circuit: &C::Inner,
+    instance: Vec<Vec<Fr>>,
+) -> anyhow::Result<ConstraintViolationReport> {
+    let prover = MockProver::<Fr>::run(k, circuit, instance)?;
+    let violations = match prover.verify_par() {
+        Ok(()) => Vec::new(),
+        Err(errs) => {
+            log::error!("err num: {}", errs.len());
+            for err in &errs {
+                log::error!("{}", err);
+            }
+            errs.iter().map(ConstraintViolation::from).collect()
+        }
+    };
+    Ok(ConstraintViolationReport {
+        circuit: C::name(),
+        violations,
+    })
+}
+
+impl ConstraintViolationReport {
+    fn into_result(self) -> Result<(), MockProveError> {
+        match self.violations.first() {
+            None => Ok(()),
+            Some(first_violation) => Err(MockProveError {
+                total_violations: self.violations.len(),
+                first_violation: first_violation.clone(),
+            }),
+        }
+    }
+}
+
 impl Prover {
+    /// Mock prove a block without a prover instance, so no [`ConstraintViolationReport`] is
+    /// kept: use [`Prover::mock_prove_circuit`] for that.
     pub fn mock_prove_target_circuit<C: TargetCircuit>(
         block_trace: &BlockTrace,
     ) -> anyhow::Result<()> {
         Self::mock_prove_target_circuit_batch::<C>(&[block_trace.clone()])
     }
 
+    /// Mock prove a batch without a prover instance, so no [`ConstraintViolationReport`] is
+    /// kept: use [`Prover::mock_prove_circuit`] for that.
     pub fn mock_prove_target_circuit_batch<C: TargetCircuit>(
         block_traces: &[BlockTrace],
     ) -> anyhow::Result<()> {
+        check_gas_accounting(block_traces)?;
+        check_calldata_size(block_traces)?;
+        check_fee_caps(block_traces)?;
         log::info!(
             "start mock prove {}, rows needed {:?}",
             C::name(),
@@ -31,14 +127,7 @@ impl Prover {
             metric_of_witness_block(&witness_block)
         );
         let (circuit, instance) = C::from_witness_block(&witness_block)?;
-        let prover = MockProver::<Fr>::run(*DEGREE as u32, &circuit, instance)?;
-        if let Err(errs) = prover.verify_par() {
-            log::error!("err num: {}", errs.len());
-            for err in &errs {
-                log::error!("{}", err);
-            }
-            bail!("{:#?}", errs);
-        }
+        run_mock_prover::<C>(*DEGREE as u32, &circuit, instance)?.into_result()?;
         log::info!(
             "mock prove {} done. block proved {}/{}, batch metric: {:?}",
             C::name(),
@@ -49,3 +138,27 @@ impl Prover {
         Ok(())
     }
 }
+
+impl<S: ProofSystem> Prover<S> {
+    /// Mock prove the circuit with the degree of the target circuit params.
+    /// The report is kept, see [`Prover::last_mock_prove_report`].
+    pub fn mock_prove_circuit<C: TargetCircuit>(
+        &mut self,
+        circuit: &C::Inner,
+        instance: Vec<Vec<Fr>>,
+    ) -> anyhow::Result<()> {
+        log::info!("mock prove {} start", C::name());
+        let report = run_mock_prover::<C>(self.params.k(), circuit, instance)?;
+        self.last_mock_prove_report = Some(report.clone());
+        report.into_result()?;
+        log::info!("mock prove {} done", C::name());
+        Ok(())
+    }
+
+    /// The report of the most recent [`Prover::mock_prove_circuit`] call, including the mock
+    /// proves done before proving when `MOCK_PROVE` is set. The static
+    /// `mock_prove_target_circuit*` functions don't keep reports.
+    pub fn last_mock_prove_report(&self) -> Option<ConstraintViolationReport> {
+        self.last_mock_prove_report.clone()
+    }
+}
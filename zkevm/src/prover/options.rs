@@ -0,0 +1,148 @@
+//! Options of a prover, and the hash of those which affect the proofs.
+
+use super::{TargetCircuitProof, OPT_MEM};
+use crate::attest::CircuitParameters;
+use crate::circuit::{SuperCircuit, TargetCircuit, AGG_DEGREE, PARALLEL_WITNESS};
+use halo2_proofs::halo2curves::bn256::Bn256;
+use halo2_proofs::poly::kzg::multiopen::ProverSHPLONK;
+use serde_derive::Serialize;
+use sha2::{Digest, Sha256};
+use snark_verifier::loader::native::NativeLoader;
+use snark_verifier_sdk::halo2::PoseidonTranscript;
+use std::fmt;
+
+/// Transcript of the target circuit proofs, the one `gen_snark_shplonk` writes.
+pub(crate) type TargetCircuitTranscript<W> = PoseidonTranscript<NativeLoader, W>;
+/// Multi-open scheme of the target circuit proofs, the one `gen_snark_shplonk` uses.
+pub(crate) type TargetCircuitMultiOpen<'params> = ProverSHPLONK<'params, Bn256>;
+
+/// Name of a transcript or multi-open scheme in the config hash.
+trait SchemeName {
+    const NAME: &'static str;
+}
+
+impl<W> SchemeName for TargetCircuitTranscript<W> {
+    const NAME: &'static str = "poseidon";
+}
+
+impl SchemeName for TargetCircuitMultiOpen<'_> {
+    const NAME: &'static str = "shplonk";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProverOptions {
+    pub circuit_parameters: CircuitParameters,
+    pub agg_degree: u32,
+    /// What the public inputs of the target circuit commit to, the names of the public inputs
+    /// of [`SuperCircuit`].
+    pub pi_mode: String,
+    /// Transcript of the target circuit proofs.
+    pub transcript: &'static str,
+    pub multi_open: &'static str,
+    /// Measure block row usage with rayon. Operational, not part of the config hash.
+    pub parallel_witness: bool,
+    /// Operational, not part of the config hash.
+    pub opt_mem: bool,
+    /// Aggregate target circuit proofs with another config hash, logging a warning instead of
+    /// failing. Operational, not part of the config hash.
+    pub allow_config_mismatch: bool,
+}
+
+/// The options which change the circuits or the proofs, in a fixed field order.
+#[derive(Serialize)]
+struct SemanticOptions<'a> {
+    circuit_parameters: &'a CircuitParameters,
+    agg_degree: u32,
+    pi_mode: &'a str,
+    transcript: &'a str,
+    multi_open: &'a str,
+}
+
+impl ProverOptions {
+    /// The options of this build and environment, for target circuit params of `degree`.
+    pub fn current(degree: u32) -> Self {
+        Self {
+            circuit_parameters: CircuitParameters::current(degree),
+            agg_degree: *AGG_DEGREE as u32,
+            pi_mode: SuperCircuit::describe()
+                .public_inputs
+                .iter()
+                .map(|field| field.name)
+                .collect::<Vec<_>>()
+                .join(","),
+            transcript: <TargetCircuitTranscript<Vec<u8>> as SchemeName>::NAME,
+            multi_open: <TargetCircuitMultiOpen<'static> as SchemeName>::NAME,
+            parallel_witness: *PARALLEL_WITNESS,
+            opt_mem: *OPT_MEM,
+            allow_config_mismatch: false,
+        }
+    }
+
+    /// sha256 over the options which affect circuit semantics, but not operational knobs.
+    pub fn config_hash(&self) -> [u8; 32] {
+        let semantic = SemanticOptions {
+            circuit_parameters: &self.circuit_parameters,
+            agg_degree: self.agg_degree,
+            pi_mode: &self.pi_mode,
+            transcript: self.transcript,
+            multi_open: self.multi_open,
+        };
+        Sha256::digest(serde_json::to_vec(&semantic).unwrap()).into()
+    }
+
+    pub fn config_hash_hex(&self) -> String {
+        hex::encode(self.config_hash())
+    }
+}
+
+/// A target circuit proof was generated with a different config than the prover's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigHashMismatch {
+    /// Index of the proof in the aggregated list.
+    pub index: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for ConfigHashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "proof {} has config hash {:?}, expected {}",
+            self.index, self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for ConfigHashMismatch {}
+
+/// Check that every proof was generated with the config hash `expected`.
+///
+/// Proofs from before config hashes were recorded have an empty `config_hash`. They are
+/// accepted with a warning, as their config cannot be checked: regenerate them to have it
+/// checked.
+pub fn check_config_hashes(
+    proofs: &[TargetCircuitProof],
+    expected: &str,
+) -> Result<(), ConfigHashMismatch> {
+    for (index, proof) in proofs.iter().enumerate() {
+        if proof.config_hash.is_empty() {
+            log::warn!(
+                "proof {} ({}) has no config hash, aggregating it unchecked",
+                index,
+                proof.name
+            );
+        }
+    }
+    match proofs
+        .iter()
+        .position(|proof| !proof.config_hash.is_empty() && proof.config_hash != expected)
+    {
+        Some(index) => Err(ConfigHashMismatch {
+            index,
+            expected: expected.to_string(),
+            found: proofs[index].config_hash.clone(),
+        }),
+        None => Ok(()),
+    }
+}
@@ -2,12 +2,13 @@
 
 use super::{AggCircuitProof, Prover};
 use crate::circuit::SuperCircuit;
-use crate::io::{serialize_fr_tensor, serialize_vk};
-use crate::prover::TargetCircuitProof;
+use crate::io::{serialize_fr_tensor, serialize_vk, vk_fingerprint};
+use crate::prover::{check_config_hashes, TargetCircuitProof};
+use halo2_proofs::halo2curves::bn256::Fr;
 use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 use snark_verifier_sdk::evm::gen_evm_proof_shplonk;
-use snark_verifier_sdk::halo2::aggregation::AggregationCircuit;
+use snark_verifier_sdk::halo2::aggregation::{AggregationCircuit, PublicAggregationCircuit};
 use snark_verifier_sdk::{gen_pk, CircuitExt};
 use types::eth::BlockTrace;
 
@@ -35,11 +36,16 @@ impl Prover {
     }
 
     /// Input the inner circuit proofs, output the aggregation proof.
+    ///
+    /// Proofs whose config hash differs from this prover's are refused with a
+    /// `ConfigHashMismatch`, unless `options.allow_config_mismatch` is set.
     pub fn create_agg_proof_by_inner_proofs(
         &mut self,
         inner_circuit_results: &[TargetCircuitProof],
         rng: &mut (impl Rng + Send),
     ) -> anyhow::Result<AggCircuitProof> {
+        self.check_inner_config_hashes(inner_circuit_results)?;
+
         let mut seed1 = [0u8; 16];
         rng.fill_bytes(&mut seed1);
         let mut seed2 = [0u8; 16];
@@ -70,16 +76,76 @@ impl Prover {
             total_block_count
         );
 
-        self.create_agg_proof_by_agg_circuit(&agg_circuit, &mut rng2, total_proved_block_count)
+        let mut proof = self.create_agg_proof_by_agg_circuit(
+            &agg_circuit,
+            &mut rng2,
+            total_proved_block_count,
+        )?;
+        proof.inner_vk_fingerprints = inner_circuit_results
+            .iter()
+            .map(|p| vk_fingerprint(&p.vk))
+            .collect();
+        Ok(proof)
+    }
+
+    fn check_inner_config_hashes(&self, proofs: &[TargetCircuitProof]) -> anyhow::Result<()> {
+        if let Err(e) = check_config_hashes(proofs, &self.options.config_hash_hex()) {
+            if !self.options.allow_config_mismatch {
+                return Err(e.into());
+            }
+            log::warn!("aggregating despite config mismatch: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Wrap a target circuit proof, generated with the native transcript, into a proof with
+    /// the EVM transcript that can be verified on chain.
+    ///
+    /// The wrapper is the aggregation circuit with a single SNARK input, which passes the
+    /// public inputs of the inner proof through: the instances of the outer proof are the
+    /// accumulator limbs followed by the inner instances.
+    pub fn wrap_for_evm(
+        &mut self,
+        inner: &TargetCircuitProof,
+        rng: &mut (impl Rng + Send),
+    ) -> anyhow::Result<AggCircuitProof> {
+        self.check_inner_config_hashes(std::slice::from_ref(inner), false)?;
+
+        let mut seed1 = [0u8; 16];
+        rng.fill_bytes(&mut seed1);
+        let mut seed2 = [0u8; 16];
+        rng.fill_bytes(&mut seed2);
+        let mut rng1 = XorShiftRng::from_seed(seed1);
+        let mut rng2 = XorShiftRng::from_seed(seed2);
+
+        let agg_circuit = PublicAggregationCircuit::new(
+            &self.agg_params,
+            vec![inner.snark.clone()],
+            false,
+            &mut rng1,
+        );
+        let mut outer = self.create_agg_proof_by_agg_circuit(
+            &agg_circuit,
+            &mut rng2,
+            inner.num_of_proved_blocks,
+        )?;
+        outer.inner_vk_fingerprints = vec![vk_fingerprint(&inner.vk)];
+        log::info!(
+            "wrapped {} proof for evm, inner vk {}, outer vk {}",
+            inner.name,
+            vk_fingerprint(&inner.vk),
+            vk_fingerprint(&outer.vk)
+        );
+        Ok(outer)
     }
 
     /// Input an aggregation circuit, output the aggregation proof.
     ///
     /// The actual work for the outer circuit prover.
     ///
-    pub fn create_agg_proof_by_agg_circuit(
+    pub fn create_agg_proof_by_agg_circuit<C: CircuitExt<Fr> + Clone>(
         &mut self,
-        agg_circuit: &AggregationCircuit,
+        agg_circuit: &C,
         rng: &mut (impl Rng + Send),
         total_proved_block_count: usize,
     ) -> anyhow::Result<AggCircuitProof> {
@@ -108,6 +174,8 @@ impl Prover {
             instance: instance_bytes,
             vk: vk_bytes,
             total_proved_block_count,
+            config_hash: self.options.config_hash_hex(),
+            inner_vk_fingerprints: vec![],
         })
     }
 }
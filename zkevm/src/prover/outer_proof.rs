@@ -6,7 +6,7 @@ use serde_derive::{Deserialize, Serialize};
 use std::path::PathBuf;
 use types::base64;
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct AggCircuitProof {
     #[serde(with = "base64")]
     pub proof: Vec<u8>,
@@ -15,6 +15,12 @@ pub struct AggCircuitProof {
     #[serde(with = "base64")]
     pub vk: Vec<u8>,
     pub total_proved_block_count: usize,
+    /// Hex of the prover's `ProverOptions::config_hash`, empty for older proofs.
+    #[serde(default)]
+    pub config_hash: String,
+    /// `vk_fingerprint`s of the aggregated target circuit proofs, in order.
+    #[serde(default)]
+    pub inner_vk_fingerprints: Vec<String>,
 }
 
 impl AggCircuitProof {
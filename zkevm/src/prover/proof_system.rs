@@ -0,0 +1,202 @@
+//! Backends which prove and verify target circuits, see [`ProofSystem`].
+
+use super::{Prover, ProverOptions};
+use crate::circuit::TargetCircuit;
+use crate::io::params_sha256;
+use halo2_proofs::halo2curves::bn256::{Bn256, G1Affine};
+use halo2_proofs::plonk::{keygen_pk2, ProvingKey, VerifyingKey};
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use once_cell::sync::OnceCell;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use snark_verifier_sdk::halo2::{gen_snark_shplonk, verify_snark_shplonk};
+use snark_verifier_sdk::Snark;
+
+/// A backend which sets up, proves and verifies the inner circuits of target circuits.
+///
+/// The params, keys and proofs are types of the backend, so a third party backend, e.g.
+/// Groth16, brings its own: its params are its structured reference string, its proving key
+/// the circuit specific setup of [`ProofSystem::setup`], and [`ProofSystem::verifying_key`]
+/// the part of it which [`ProofSystem::verify`] needs. The circuits are given as halo2
+/// circuits, [`TargetCircuit::Inner`], which a backend synthesizes into its own constraint
+/// system.
+///
+/// The prover sets up each circuit once, and keeps one proving key per circuit name in
+/// `target_circuit_pks`. [`Prover::prove_with_system`] and [`Prover::verify_with_system`] work
+/// with any backend; the target circuit proofs, e.g. from
+/// [`Prover::create_target_circuit_proof_batch`], are halo2 SNARKs, so they take a backend with
+/// the types of [`Halo2PlonkSystem`]. Aggregation and the evm verifier take those proofs.
+pub trait ProofSystem {
+    /// Params shared by the setups of all circuits, e.g. a KZG SRS.
+    type Params;
+    type ProvingKey;
+    type VerifyingKey;
+    type Proof;
+
+    /// The params to set up, prove and verify with: either `kzg_params`, the params of the
+    /// target circuits of the prover, or params of the backend.
+    fn params<'a>(&'a self, kzg_params: &'a ParamsKZG<Bn256>) -> &'a Self::Params;
+
+    /// Generate the proving key of circuit `C`, from a circuit with the shape of any instance
+    /// of it.
+    fn setup<C: TargetCircuit>(
+        &self,
+        params: &Self::Params,
+        circuit: &C::Inner,
+    ) -> anyhow::Result<Self::ProvingKey>;
+
+    /// The verifying key of a proving key from [`ProofSystem::setup`].
+    fn verifying_key(pk: &Self::ProvingKey) -> &Self::VerifyingKey;
+
+    fn prove<C: TargetCircuit>(
+        &self,
+        params: &Self::Params,
+        pk: &Self::ProvingKey,
+        circuit: C::Inner,
+        rng: &mut (impl Rng + Send),
+    ) -> anyhow::Result<Self::Proof>;
+
+    fn verify<C: TargetCircuit>(
+        &self,
+        params: &Self::Params,
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+    ) -> anyhow::Result<bool>;
+}
+
+/// halo2 PLONK with KZG commitments and SHPLONK multi-open, the proofs aggregation takes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Halo2PlonkSystem;
+
+impl ProofSystem for Halo2PlonkSystem {
+    type Params = ParamsKZG<Bn256>;
+    type ProvingKey = ProvingKey<G1Affine>;
+    type VerifyingKey = VerifyingKey<G1Affine>;
+    type Proof = Snark;
+
+    fn params<'a>(&'a self, kzg_params: &'a ParamsKZG<Bn256>) -> &'a Self::Params {
+        kzg_params
+    }
+
+    fn setup<C: TargetCircuit>(
+        &self,
+        params: &Self::Params,
+        circuit: &C::Inner,
+    ) -> anyhow::Result<Self::ProvingKey> {
+        Ok(keygen_pk2(params, circuit)?)
+    }
+
+    fn verifying_key(pk: &Self::ProvingKey) -> &Self::VerifyingKey {
+        pk.get_vk()
+    }
+
+    fn prove<C: TargetCircuit>(
+        &self,
+        params: &Self::Params,
+        pk: &Self::ProvingKey,
+        circuit: C::Inner,
+        rng: &mut (impl Rng + Send),
+    ) -> anyhow::Result<Self::Proof> {
+        Ok(gen_snark_shplonk(params, pk, circuit, rng, None::<String>))
+    }
+
+    fn verify<C: TargetCircuit>(
+        &self,
+        params: &Self::Params,
+        vk: &Self::VerifyingKey,
+        proof: &Self::Proof,
+    ) -> anyhow::Result<bool> {
+        Ok(verify_snark_shplonk::<C::Inner>(
+            params.verifier_params(),
+            proof.clone(),
+            vk,
+        ))
+    }
+}
+
+impl<S: ProofSystem> Prover<S> {
+    /// Build a prover whose target circuit proofs are made by `system`.
+    pub fn with_system(
+        params: ParamsKZG<Bn256>,
+        agg_params: ParamsKZG<Bn256>,
+        rng: XorShiftRng,
+        system: S,
+    ) -> Self {
+        let options = ProverOptions::current(params.k());
+        Self {
+            params,
+            params_sha256: OnceCell::new(),
+            agg_params,
+            rng,
+            target_circuit_pks: Default::default(),
+            agg_pk: None,
+            options,
+            running_state_root: None,
+            last_mock_prove_report: None,
+            system,
+        }
+    }
+
+    /// sha256 of the target circuit params, as serialized in a params file.
+    pub fn params_sha256(&self) -> &str {
+        self.params_sha256
+            .get_or_init(|| params_sha256(&self.params))
+    }
+
+    /// Memory usage tracker.
+    pub(crate) fn tick(desc: &str) {
+        #[cfg(target_os = "linux")]
+        let memory = match procfs::Meminfo::new() {
+            Ok(m) => m.mem_total - m.mem_free,
+            Err(_) => 0,
+        };
+        #[cfg(not(target_os = "linux"))]
+        let memory = 0;
+        log::debug!(
+            "memory usage when {}: {:?}GB",
+            desc,
+            memory / 1024 / 1024 / 1024
+        );
+    }
+
+    /// Initiates the public key for a given inner circuit.
+    pub(crate) fn init_pk<C: TargetCircuit>(&mut self, circuit: &<C as TargetCircuit>::Inner) {
+        Self::tick(&format!("before init pk of {}", C::name()));
+        let pk = self
+            .system
+            .setup::<C>(self.system.params(&self.params), circuit)
+            .unwrap_or_else(|e| panic!("failed to generate {} pk: {:?}", C::name(), e));
+        self.target_circuit_pks.insert(C::name(), pk);
+        Self::tick(&format!("after init pk of {}", C::name()));
+    }
+
+    fn init_pk_if_missing<C: TargetCircuit>(&mut self) {
+        if !self.target_circuit_pks.contains_key(&C::name()) {
+            self.init_pk::<C>(&C::dummy_inner_circuit());
+        }
+    }
+
+    /// Prove `circuit` with the proof system of the prover.
+    pub fn prove_with_system<C: TargetCircuit>(
+        &mut self,
+        circuit: C::Inner,
+        rng: &mut (impl Rng + Send),
+    ) -> anyhow::Result<S::Proof> {
+        self.init_pk_if_missing::<C>();
+        let pk = &self.target_circuit_pks[&C::name()];
+        self.system
+            .prove::<C>(self.system.params(&self.params), pk, circuit, rng)
+    }
+
+    /// Verify a proof of circuit `C` made by [`Self::prove_with_system`].
+    pub fn verify_with_system<C: TargetCircuit>(
+        &mut self,
+        proof: &S::Proof,
+    ) -> anyhow::Result<bool> {
+        self.init_pk_if_missing::<C>();
+        let vk = S::verifying_key(&self.target_circuit_pks[&C::name()]);
+        self.system
+            .verify::<C>(self.system.params(&self.params), vk, proof)
+    }
+}
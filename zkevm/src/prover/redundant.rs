@@ -0,0 +1,160 @@
+//! Prove the same batch twice with independent seeds, and require both proofs to agree
+
+use super::{Prover, TargetCircuitProof};
+use crate::circuit::TargetCircuit;
+use crate::io::vk_fingerprint;
+use anyhow::{bail, Result};
+use halo2_proofs::poly::commitment::ParamsProver;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use snark_verifier_sdk::halo2::verify_snark_shplonk;
+use std::fmt;
+use types::eth::BlockTrace;
+
+/// Seed of the rng used by one proving run.
+pub type Seed = [u8; 16];
+
+/// Both proofs of a redundant run. They verified, share the vk and commit to the same
+/// public inputs.
+#[derive(Debug)]
+pub struct RedundantProofResult {
+    pub proofs: [TargetCircuitProof; 2],
+    /// Id of each run, derived from the circuit name and the seed.
+    pub run_ids: [String; 2],
+    pub vk_fingerprint: String,
+}
+
+/// The two proofs of a redundant run diverge on `field`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedundancyMismatch {
+    pub field: String,
+    pub first: String,
+    pub second: String,
+}
+
+impl fmt::Display for RedundancyMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "redundant proofs diverge on {}: {} vs {}",
+            self.field, self.first, self.second
+        )
+    }
+}
+
+impl std::error::Error for RedundancyMismatch {}
+
+fn run_id(name: &str, seed: &Seed) -> String {
+    format!("{}-{}", name, hex::encode(seed))
+}
+
+/// Compare the config hash, the vk and the public inputs of the two proofs of a redundant run.
+pub fn check_redundant_proofs(proofs: &[TargetCircuitProof; 2]) -> Result<(), RedundancyMismatch> {
+    let [first, second] = proofs;
+    let mismatch = |field: String, first: String, second: String| {
+        Err(RedundancyMismatch {
+            field,
+            first,
+            second,
+        })
+    };
+
+    if first.config_hash != second.config_hash {
+        return mismatch(
+            "config_hash".to_string(),
+            first.config_hash.clone(),
+            second.config_hash.clone(),
+        );
+    }
+
+    let vks = [vk_fingerprint(&first.vk), vk_fingerprint(&second.vk)];
+    if vks[0] != vks[1] {
+        let [first, second] = vks;
+        return mismatch("vk".to_string(), first, second);
+    }
+
+    let instances = [&first.snark.instances, &second.snark.instances];
+    if instances[0].len() != instances[1].len() {
+        return mismatch(
+            "instances.len()".to_string(),
+            instances[0].len().to_string(),
+            instances[1].len().to_string(),
+        );
+    }
+    for (i, (column1, column2)) in instances[0].iter().zip(instances[1]).enumerate() {
+        if column1.len() != column2.len() {
+            return mismatch(
+                format!("instances[{i}].len()"),
+                column1.len().to_string(),
+                column2.len().to_string(),
+            );
+        }
+        if let Some(j) = (0..column1.len()).find(|&j| column1[j] != column2[j]) {
+            return mismatch(
+                format!("instances[{i}][{j}]"),
+                format!("{:?}", column1[j]),
+                format!("{:?}", column2[j]),
+            );
+        }
+    }
+    Ok(())
+}
+
+impl Prover {
+    /// Prove the batch once per seed, verify both proofs and check that they commit to the same
+    /// public inputs under the same vk. Any divergence is a [`RedundancyMismatch`] error.
+    ///
+    /// The runs are sequential, so the memory needed is the one of a single proof.
+    pub fn prove_redundant<C: TargetCircuit>(
+        &mut self,
+        block_traces: &[BlockTrace],
+        seeds: [Seed; 2],
+    ) -> Result<RedundantProofResult> {
+        self.prove_redundant_with::<C>(seeds, |prover, _run, rng| {
+            prover.create_target_circuit_proof_batch::<C>(block_traces, rng)
+        })
+    }
+
+    /// [`Prover::prove_redundant`] with the proving of each run done by `prove`, which gets the
+    /// index of the run and the rng seeded for it. The proof it returns must be of circuit `C`.
+    pub fn prove_redundant_with<C: TargetCircuit>(
+        &mut self,
+        seeds: [Seed; 2],
+        mut prove: impl FnMut(&mut Self, usize, &mut XorShiftRng) -> Result<TargetCircuitProof>,
+    ) -> Result<RedundantProofResult> {
+        if seeds[0] == seeds[1] {
+            bail!("redundant proving needs two different seeds");
+        }
+        let run_ids = seeds.map(|seed| run_id(&C::name(), &seed));
+        log::info!("redundant proving, runs {} and {}", run_ids[0], run_ids[1]);
+
+        let mut proofs = Vec::with_capacity(2);
+        for (run, (seed, id)) in seeds.iter().zip(&run_ids).enumerate() {
+            let mut rng = XorShiftRng::from_seed(*seed);
+            let proof = prove(self, run, &mut rng)?;
+
+            let vk = self.target_circuit_pks[&C::name()].get_vk();
+            if !verify_snark_shplonk::<C::Inner>(
+                self.params.verifier_params(),
+                proof.snark.clone(),
+                vk,
+            ) {
+                bail!("proof of redundant run {} failed verification", id);
+            }
+            proofs.push(proof);
+        }
+        let proofs: [TargetCircuitProof; 2] = proofs.try_into().unwrap();
+
+        check_redundant_proofs(&proofs)?;
+        log::info!(
+            "redundant runs {} and {} are consistent",
+            run_ids[0],
+            run_ids[1]
+        );
+        Ok(RedundantProofResult {
+            vk_fingerprint: vk_fingerprint(&proofs[0].vk),
+            proofs,
+            run_ids,
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! Proving with the proof bytes streamed out while the transcript is written
+
+use super::inner_circuit::target_circuit_from_traces;
+use super::options::{TargetCircuitMultiOpen, TargetCircuitTranscript};
+use super::{Prover, MOCK_PROVE};
+use crate::circuit::TargetCircuit;
+use crate::io::StreamingProofUploader;
+use anyhow::bail;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+use halo2_proofs::plonk::create_proof;
+use halo2_proofs::poly::kzg::commitment::KZGCommitmentScheme;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use snark_verifier_sdk::halo2::POSEIDON_SPEC;
+use snark_verifier_sdk::CircuitExt;
+use std::io::Write;
+use types::eth::BlockTrace;
+
+impl Prover {
+    /// Prove the traces with circuit `C`, writing the proof bytes to `uploader` as the
+    /// transcript is committed, so a long proving run which dies leaves a partial upload instead
+    /// of nothing. The rng is derived from the prover's.
+    ///
+    /// Only the proof bytes are uploaded, they are the same as the ones of
+    /// [`Self::create_target_circuit_proof_batch`] for the same rng.
+    pub fn create_proof_streaming_upload<C: TargetCircuit>(
+        &mut self,
+        block_traces: &[BlockTrace],
+        uploader: StreamingProofUploader,
+    ) -> anyhow::Result<()> {
+        let ((circuit, _), _) = target_circuit_from_traces::<C>(block_traces)?;
+        let mut rng = XorShiftRng::from_rng(&mut self.rng)?;
+        self.create_target_circuit_proof_streaming::<C>(circuit, &mut rng, uploader)
+    }
+
+    /// Prove `circuit`, writing the proof bytes to `uploader` as the transcript is committed.
+    pub fn create_target_circuit_proof_streaming<C: TargetCircuit>(
+        &mut self,
+        circuit: C::Inner,
+        rng: &mut (impl Rng + Send),
+        uploader: StreamingProofUploader,
+    ) -> anyhow::Result<()> {
+        let instances = circuit.instances();
+        if *MOCK_PROVE {
+            self.mock_prove_circuit::<C>(&circuit, instances.clone())?;
+        }
+
+        if !self.target_circuit_pks.contains_key(&C::name()) {
+            self.init_pk::<C>(&C::dummy_inner_circuit());
+        }
+        let pk = &self.target_circuit_pks[&C::name()];
+
+        let instances: Vec<&[Fr]> = instances.iter().map(Vec::as_slice).collect();
+        let mut transcript = TargetCircuitTranscript::from_spec(uploader, POSEIDON_SPEC.clone());
+        if let Err(e) = create_proof::<KZGCommitmentScheme<Bn256>, TargetCircuitMultiOpen, _, _, _, _>(
+            &self.params,
+            pk,
+            &[circuit],
+            &[&instances],
+            rng,
+            &mut transcript,
+        ) {
+            bail!("failed to create {} proof: {:?}", C::name(), e);
+        }
+
+        let mut uploader = transcript.finalize();
+        uploader.flush()?;
+        log::info!(
+            "{} circuit: streamed {} proof bytes",
+            C::name(),
+            uploader.bytes_uploaded()
+        );
+        Ok(())
+    }
+}
@@ -1,51 +1,20 @@
 //! Initialization and utility APIs for Prover.
 //!
-use super::Prover;
-use crate::circuit::{TargetCircuit, AGG_DEGREE, DEGREE};
+use super::{Halo2PlonkSystem, Prover};
+use crate::circuit::{SuperCircuit, TargetCircuit, AGG_DEGREE, DEGREE};
 use crate::utils::load_or_create_params;
-use crate::utils::load_seed;
+use crate::utils::{load_seed, seed_from_env};
 use halo2_proofs::halo2curves::bn256::Bn256;
-use halo2_proofs::plonk::keygen_pk2;
-use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
 use halo2_proofs::poly::kzg::commitment::{ParamsKZG, ParamsVerifierKZG};
-use rand::SeedableRng;
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
 impl Prover {
     /// Build a new Prover from parameters.
     pub fn new(params: ParamsKZG<Bn256>, agg_params: ParamsKZG<Bn256>, rng: XorShiftRng) -> Self {
-        Self {
-            params,
-            agg_params,
-            rng,
-            target_circuit_pks: Default::default(),
-            agg_pk: None,
-        }
-    }
-
-    /// Memory usage tracker.
-    pub(crate) fn tick(desc: &str) {
-        #[cfg(target_os = "linux")]
-        let memory = match procfs::Meminfo::new() {
-            Ok(m) => m.mem_total - m.mem_free,
-            Err(_) => 0,
-        };
-        #[cfg(not(target_os = "linux"))]
-        let memory = 0;
-        log::debug!(
-            "memory usage when {}: {:?}GB",
-            desc,
-            memory / 1024 / 1024 / 1024
-        );
-    }
-
-    /// Initiates the public key for a given inner circuit.
-    pub(crate) fn init_pk<C: TargetCircuit>(&mut self, circuit: &<C as TargetCircuit>::Inner) {
-        Self::tick(&format!("before init pk of {}", C::name()));
-        let pk = keygen_pk2(&self.params, circuit)
-            .unwrap_or_else(|e| panic!("failed to generate {} pk: {:?}", C::name(), e));
-        self.target_circuit_pks.insert(C::name(), pk);
-        Self::tick(&format!("after init pk of {}", C::name()));
+        Self::with_system(params, agg_params, rng, Halo2PlonkSystem)
     }
 
     pub fn from_params_and_rng(
@@ -76,11 +45,32 @@ impl Prover {
         Self::from_params_and_rng(params, agg_params, rng)
     }
 
-    pub fn from_fpath(params_fpath: &str, seed_fpath: &str) -> Self {
+    /// Load or create the params in `params_fpath`, and seed the rng from the `SEED` env var,
+    /// or else from the file at `seed_fpath`.
+    ///
+    /// Without either, the seed is drawn from the OS rng, so the proofs of this prover are
+    /// not reproducible.
+    pub fn from_fpath(params_fpath: &str, seed_fpath: Option<&str>) -> Self {
         let params = load_or_create_params(params_fpath, *DEGREE).expect("failed to init params");
         let agg_params =
             load_or_create_params(params_fpath, *AGG_DEGREE).expect("failed to init params");
-        let seed = load_seed(seed_fpath).expect("failed to init rng");
+        if params.k() < SuperCircuit::required_params_degree() {
+            log::warn!(
+                "params degree {} is less than {} required by the super circuit",
+                params.k(),
+                SuperCircuit::required_params_degree()
+            );
+        }
+        let seed = match (seed_from_env().expect("invalid SEED"), seed_fpath) {
+            (Some(seed), _) => seed,
+            (None, Some(seed_fpath)) => load_seed(seed_fpath).expect("failed to init rng"),
+            (None, None) => {
+                log::warn!("no seed file, proofs will not be deterministic");
+                let mut seed = [0u8; 16];
+                OsRng.fill_bytes(&mut seed);
+                seed
+            }
+        };
         Self::from_params_and_seed(params, agg_params, seed)
     }
 }
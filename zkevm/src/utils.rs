@@ -1,24 +1,38 @@
 use anyhow::Result;
-use halo2_proofs::arithmetic::Field;
-use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+use eth_types::evm_types::OpcodeId;
+use eth_types::H256;
+use halo2_proofs::arithmetic::{parallelize, Field};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine, G1};
+use halo2_proofs::halo2curves::group::{ff::PrimeField, prime::PrimeCurveAffine, Curve, Group};
+use halo2_proofs::halo2curves::serde::SerdeObject;
 use halo2_proofs::halo2curves::FieldExt;
 use halo2_proofs::SerdeFormat;
 
 use halo2_proofs::poly::kzg::commitment::ParamsKZG;
 use rand::rngs::OsRng;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs::{self, metadata, File};
-use std::io::{BufReader, Read, Write};
+use std::io::{BufRead, BufReader, Read, Seek, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use types::eth::{BlockTrace, BlockTraceJsonRpcResult};
+use types::eth::{
+    BlockTrace, BlockTraceJsonRpcResult, BlockTraceLite, BlockTraceLiteJsonRpcResult,
+};
 use zkevm_circuits::witness;
 
 pub(crate) const DEFAULT_SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytesUnchecked;
 
+/// Path of the params file of `degree` inside `params_dir`, i.e. `<params_dir>/params<degree>`,
+/// for example `params20` for degree 20.
+pub fn params_path_for_degree(params_dir: &Path, degree: u32) -> PathBuf {
+    params_dir.join(format!("params{degree}"))
+}
+
 /// return setup params by reading from file or generate new one
 pub fn load_or_create_params(params_dir: &str, degree: usize) -> Result<ParamsKZG<Bn256>> {
-    let _path = PathBuf::from(params_dir);
-
     match metadata(params_dir) {
         Ok(md) => {
             if md.is_file() {
@@ -31,7 +45,9 @@ pub fn load_or_create_params(params_dir: &str, degree: usize) -> Result<ParamsKZ
         }
     };
 
-    let params_path = format!("{params_dir}/params{degree}");
+    let params_path = params_path_for_degree(Path::new(params_dir), degree as u32)
+        .to_string_lossy()
+        .into_owned();
     log::info!("load_or_create_params {}", params_path);
     if Path::new(&params_path).exists() {
         match load_params(&params_path, degree, DEFAULT_SERDE_FORMAT) {
@@ -51,11 +67,14 @@ pub fn load_params(
     serde_format: SerdeFormat,
 ) -> Result<ParamsKZG<Bn256>> {
     log::info!("start loading params with degree {}", degree);
+    if params_dir.ends_with(".partial") {
+        anyhow::bail!("refusing to load partially generated params {}", params_dir);
+    }
     let params_path = if metadata(params_dir)?.is_dir() {
         // auto load
-        format!("{params_dir}/params{degree}")
+        params_path_for_degree(Path::new(params_dir), degree as u32)
     } else {
-        params_dir.to_string()
+        PathBuf::from(params_dir)
     };
     let f = File::open(params_path)?;
 
@@ -84,37 +103,322 @@ pub fn load_params(
 }
 
 /// create params and write it into file
+///
+/// Generation goes through `<params_path>.partial`, see [`create_params_partial`], so an
+/// interrupted run is resumed (or discarded) instead of leaving a truncated params file.
 pub fn create_params(params_path: &str, degree: usize) -> Result<ParamsKZG<Bn256>> {
     log::info!("start creating params with degree {}", degree);
+    create_params_partial(params_path, degree, usize::MAX)?;
+    let params = load_params(params_path, degree, DEFAULT_SERDE_FORMAT)?;
+    log::info!("create params successfully!");
+
+    Ok(params)
+}
+
+/// Number of g1 points generated between two progress markers.
+const PARAMS_CHUNK_POINTS: usize = 1 << 16;
+
+/// Progress of a params file generation, see [`params_generation_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamsGenerationStatus {
+    /// Neither the params file nor a partial one exists.
+    Missing,
+    /// Generation was started but not finished. `points_written` is the count recorded by the
+    /// last progress marker, out of `total_points` g1 points.
+    Partial {
+        points_written: usize,
+        total_points: usize,
+    },
+    /// The params file is complete.
+    Complete,
+}
+
+/// Progress marker stored next to the partial params file.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct ParamsProgress {
+    degree: usize,
+    /// sha256 of the setup secret, so a partial file is never resumed with another `PARAM_SEED`.
+    seed_hash: String,
+    points_written: usize,
+}
+
+fn partial_params_path(params_path: &str) -> String {
+    format!("{params_path}.partial")
+}
+
+fn params_progress_path(params_path: &str) -> String {
+    format!("{params_path}.partial.progress")
+}
+
+fn read_params_progress(params_path: &str) -> Option<ParamsProgress> {
+    let progress = fs::read(params_progress_path(params_path)).ok()?;
+    serde_json::from_slice(&progress).ok()
+}
+
+/// Report whether the params at `params_path` are complete, partially generated or missing.
+pub fn params_generation_status(params_path: &str) -> ParamsGenerationStatus {
+    if Path::new(params_path).exists() {
+        return ParamsGenerationStatus::Complete;
+    }
+    if !Path::new(&partial_params_path(params_path)).exists() {
+        return ParamsGenerationStatus::Missing;
+    }
+    match read_params_progress(params_path) {
+        Some(progress) => ParamsGenerationStatus::Partial {
+            points_written: progress.points_written,
+            total_points: 2 << progress.degree,
+        },
+        // no marker yet: nothing in the partial file is usable, only its header tells the degree
+        None => {
+            let mut k = [0u8; 4];
+            let total_points = File::open(partial_params_path(params_path))
+                .and_then(|mut f| f.read_exact(&mut k))
+                .map(|_| 2 << u32::from_le_bytes(k))
+                .unwrap_or(0);
+            ParamsGenerationStatus::Partial {
+                points_written: 0,
+                total_points,
+            }
+        }
+    }
+}
+
+/// The setup secret, and its hash when it is reproducible across runs.
+fn params_secret() -> (Fr, Option<String>) {
     // The params used for production need to be generated from a trusted setup ceremony.
     // Here we use a deterministic seed to generate params. This method is unsafe for production usage.
     let seed_str = read_env_var("PARAM_SEED", "bb4b94a1bbef58c4b5fcda6c900629b5".to_string());
-    let seed_fr = if seed_str.is_empty() {
+    if seed_str.is_empty() {
         log::info!("use OsRng to create params");
-        Fr::random(OsRng)
-    } else {
-        let bytes = &mut [0u8; 64];
-        bytes[..32].clone_from_slice(&seed_str.as_bytes()[..32]);
-        Fr::from_bytes_wide(bytes)
+        return (Fr::random(OsRng), None);
+    }
+    let bytes = &mut [0u8; 64];
+    bytes[..32].clone_from_slice(&seed_str.as_bytes()[..32]);
+    let s = Fr::from_bytes_wide(bytes);
+    (s, Some(hex::encode(Sha256::digest(s.to_repr()))))
+}
+
+/// Compute the g1 points `[start, start + len)` of the `write_custom` layout, i.e. `g` followed
+/// by `g_lagrange`, exactly as `ParamsKZG::unsafe_setup_with_s` does.
+fn params_g1_points(degree: usize, s: Fr, start: usize, len: usize) -> Vec<G1Affine> {
+    let n = 1usize << degree;
+    let g1 = G1Affine::generator();
+
+    let mut root = Fr::ROOT_OF_UNITY_INV.invert().unwrap();
+    for _ in degree as u32..Fr::S {
+        root = root.square();
+    }
+    let n_inv = Fr::from(n as u64).invert().unwrap();
+    let multiplier = (s.pow_vartime([n as u64]) - Fr::one()) * n_inv;
+
+    let mut projective = vec![G1::identity(); len];
+    parallelize(&mut projective, |points, offset| {
+        let first = start + offset;
+        let mut current_g: G1 = g1 * s.pow_vartime([first as u64]);
+        for (i, point) in points.iter_mut().enumerate() {
+            let idx = first + i;
+            *point = if idx < n {
+                let g = current_g;
+                current_g *= s;
+                g
+            } else {
+                let root_pow = root.pow_vartime([(idx - n) as u64]);
+                g1 * (multiplier * root_pow * (s - root_pow).invert().unwrap())
+            };
+        }
+    });
+    let mut affine = vec![G1Affine::identity(); len];
+    G1::batch_normalize(&projective, &mut affine);
+    affine
+}
+
+/// Generate at most `max_points` more g1 points into `<params_path>.partial`, resuming from its
+/// last progress marker, and move the file to `params_path` once it is complete.
+///
+/// A partial file without a usable marker, or generated for another degree or `PARAM_SEED`, is
+/// discarded. Bytes written after the last marker are dropped before resuming.
+pub fn create_params_partial(
+    params_path: &str,
+    degree: usize,
+    max_points: usize,
+) -> Result<ParamsGenerationStatus> {
+    let partial_path = partial_params_path(params_path);
+    let progress_path = params_progress_path(params_path);
+    let total_points = 2 << degree;
+    let (s, seed_hash) = params_secret();
+
+    let resume_from = match (read_params_progress(params_path), &seed_hash) {
+        (Some(progress), Some(seed_hash))
+            if Path::new(&partial_path).exists()
+                && progress.degree == degree
+                && &progress.seed_hash == seed_hash
+                && progress.points_written <= total_points
+                && metadata(&partial_path)
+                    .map(|md| md.len() >= 4 + (progress.points_written * 64) as u64)
+                    .unwrap_or(false) =>
+        {
+            log::info!(
+                "resuming params generation at {}/{} points from {}",
+                progress.points_written,
+                total_points,
+                partial_path
+            );
+            Some(progress.points_written)
+        }
+        _ => {
+            if Path::new(&partial_path).exists() {
+                log::warn!("discarding unusable partial params file {}", partial_path);
+            }
+            let _ = fs::remove_file(&progress_path);
+            None
+        }
     };
-    let params: ParamsKZG<Bn256> = ParamsKZG::<Bn256>::unsafe_setup_with_s(degree as u32, seed_fr);
-    let mut params_buf = Vec::new();
-    params.write_custom(&mut params_buf, DEFAULT_SERDE_FORMAT)?;
 
-    let mut params_file = File::create(params_path)?;
-    params_file.write_all(&params_buf[..])?;
-    log::info!("create params successfully!");
+    let mut points_written = resume_from.unwrap_or(0);
+    if seed_hash.is_none() && max_points < total_points {
+        anyhow::bail!("params generated from a random seed can not be resumed");
+    }
 
-    Ok(params)
+    let mut partial = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .open(&partial_path)?;
+    // drop whatever was written after the last marker
+    partial.set_len(4 + (points_written * 64) as u64)?;
+    let mut partial = std::io::BufWriter::new(partial);
+    if resume_from.is_none() {
+        partial.write_all(&(degree as u32).to_le_bytes())?;
+    }
+    partial.seek(std::io::SeekFrom::End(0))?;
+
+    let stop_at = total_points.min(points_written.saturating_add(max_points));
+    while points_written < stop_at {
+        let len = PARAMS_CHUNK_POINTS.min(stop_at - points_written);
+        for point in params_g1_points(degree, s, points_written, len) {
+            point.write_raw(&mut partial)?;
+        }
+        partial.flush()?;
+        partial.get_ref().sync_data()?;
+        points_written += len;
+
+        let progress = ParamsProgress {
+            degree,
+            seed_hash: seed_hash.clone().unwrap_or_default(),
+            points_written,
+        };
+        fs::write(&progress_path, serde_json::to_vec(&progress)?)?;
+        log::debug!(
+            "params generation progress {}/{}",
+            points_written,
+            total_points
+        );
+    }
+
+    if points_written < total_points {
+        return Ok(ParamsGenerationStatus::Partial {
+            points_written,
+            total_points,
+        });
+    }
+
+    let g2 = G2Affine::generator();
+    let s_g2: G2Affine = (g2 * s).into();
+    g2.write_raw(&mut partial)?;
+    s_g2.write_raw(&mut partial)?;
+    partial.flush()?;
+    partial.get_ref().sync_all()?;
+    drop(partial);
+
+    fs::rename(&partial_path, params_path)?;
+    let _ = fs::remove_file(&progress_path);
+    Ok(ParamsGenerationStatus::Complete)
 }
 
-/// return random seed by reading from file or generate new one
-pub fn load_or_create_seed(seed_path: &str) -> Result<[u8; 16]> {
-    if Path::new(seed_path).exists() {
-        load_seed(seed_path)
-    } else {
-        create_seed(seed_path)
+/// Neither a seed from the environment, nor a seed file, nor a writable directory to create one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedUnavailable {
+    pub seed_path: PathBuf,
+}
+
+impl std::fmt::Display for SeedUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "no seed file at {:?} and its directory is not writable; either set SEED to 32 hex \
+             characters, or mount the directory writable so the seed file can be created",
+            self.seed_path
+        )
+    }
+}
+
+impl std::error::Error for SeedUnavailable {}
+
+/// Parse a seed given as 32 hex characters, with an optional `0x` prefix.
+pub fn parse_seed_hex(seed_hex: &str) -> Result<[u8; 16]> {
+    let seed_hex = seed_hex.trim();
+    let bytes = hex::decode(seed_hex.strip_prefix("0x").unwrap_or(seed_hex))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| anyhow::anyhow!("seed is {} bytes, expected 16", bytes.len()))
+}
+
+/// The seed of the `SEED` env var, if set. It never touches the disk.
+pub fn seed_from_env() -> Result<Option<[u8; 16]>> {
+    let seed_hex = read_env_var("SEED", String::new());
+    if seed_hex.is_empty() {
+        return Ok(None);
+    }
+    parse_seed_hex(&seed_hex).map(Some)
+}
+
+/// Whether files can be created in `dir`, probed by creating and removing an empty file. The
+/// permission bits alone don't tell about read-only mounts.
+pub fn is_dir_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".write_probe.{}", std::process::id()));
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Get the seed from, in order: `seed_hex`, the file at `seed_path`, or a new seed file at
+/// `seed_path` if its directory is writable. An existing seed file is only read, so it can live
+/// on a read-only mount.
+pub fn resolve_seed(seed_path: &str, seed_hex: Option<&str>) -> Result<[u8; 16]> {
+    if let Some(seed_hex) = seed_hex {
+        return parse_seed_hex(seed_hex);
+    }
+    let path = Path::new(seed_path);
+    if path.exists() {
+        return load_seed(seed_path);
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !is_dir_writable(dir) {
+        return Err(SeedUnavailable {
+            seed_path: path.to_path_buf(),
+        }
+        .into());
     }
+    create_seed(seed_path)
+}
+
+/// return the seed of the `SEED` env var, or read it from file, or generate new one
+pub fn load_or_create_seed(seed_path: &str) -> Result<[u8; 16]> {
+    let seed_hex = read_env_var("SEED", String::new());
+    resolve_seed(
+        seed_path,
+        Some(seed_hex.as_str()).filter(|hex| !hex.is_empty()),
+    )
 }
 
 /// load seed from the file
@@ -159,6 +463,403 @@ pub fn get_block_trace_from_file<P: AsRef<Path>>(path: P) -> BlockTrace {
     })
 }
 
+/// Failure to load a batch of traces.
+#[derive(Debug)]
+pub enum BatchError {
+    Io(std::io::Error),
+    InvalidTrace {
+        path: String,
+        reason: String,
+    },
+    /// Loading the next trace would exceed the memory budget.
+    ExceededMemoryBudget {
+        loaded: usize,
+        skipped: usize,
+    },
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read batch: {e}"),
+            Self::InvalidTrace { path, reason } => write!(f, "invalid trace {path}: {reason}"),
+            Self::ExceededMemoryBudget { loaded, skipped } => write!(
+                f,
+                "batch exceeds memory budget, {loaded} traces loaded, {skipped} skipped"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<std::io::Error> for BatchError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(serde_derive::Deserialize)]
+struct HeaderNumberOnly {
+    number: Option<ethers_core::types::U64>,
+}
+
+/// Visit a trace, or a json rpc result wrapping one, up to its header only.
+struct HeaderNumberVisitor<'a> {
+    number: &'a mut Option<u64>,
+}
+
+impl<'de> serde::de::Visitor<'de> for HeaderNumberVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a block trace")
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "header" => {
+                    let header = map.next_value::<HeaderNumberOnly>()?;
+                    *self.number = Some(header.number.unwrap_or_default().as_u64());
+                    return Ok(());
+                }
+                "result" => return map.next_value_seed(self),
+                _ => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de> serde::de::DeserializeSeed<'de> for HeaderNumberVisitor<'_> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// Sort key of a trace: traces without a block number go last.
+fn block_number_sort_key(block_trace: &BlockTrace) -> (bool, Option<u64>) {
+    let number = block_trace.header.number.map(|number| number.as_u64());
+    (number.is_none(), number)
+}
+
+/// Sort traces by block number. The sort is stable, and traces without a block number go last.
+pub fn sort_traces_by_block_number(block_traces: &mut [BlockTrace]) {
+    block_traces.sort_by_key(block_number_sort_key);
+}
+
+/// Sort traces paired with their names, e.g. file names, like [`sort_traces_by_block_number`].
+pub fn sort_named_traces_by_block_number(named_traces: &mut [(String, BlockTrace)]) {
+    named_traces.sort_by_key(|(_, block_trace)| block_number_sort_key(block_trace));
+}
+
+/// Read the block number of a trace file. The file is streamed up to the header only, which
+/// comes before the bulky execution results.
+fn block_number_from_file(path: &str) -> Result<u64, BatchError> {
+    use serde::Deserializer;
+
+    let mut number = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(File::open(path)?));
+    // stopping at the header leaves the rest of the document unread, which the deserializer
+    // reports as an error once the number is already known
+    let result = deserializer.deserialize_map(HeaderNumberVisitor {
+        number: &mut number,
+    });
+    match (number, result) {
+        (Some(number), _) => Ok(number),
+        (None, result) => Err(BatchError::InvalidTrace {
+            path: path.to_string(),
+            reason: result
+                .err()
+                .map_or("missing header".to_string(), |e| e.to_string()),
+        }),
+    }
+}
+
+/// Load all the traces of `batch_dir`, sorted by block number, failing once the total size of
+/// the trace files exceeds `max_bytes`.
+pub fn load_batch_traces_bounded(
+    batch_dir: &str,
+    max_bytes: usize,
+) -> Result<(Vec<String>, Vec<BlockTrace>), BatchError> {
+    let mut file_names = Vec::new();
+    let paths = glob::glob(&format!("{batch_dir}/**/*.json"))
+        .map_err(|e| BatchError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+    for path in paths {
+        let path = path.map_err(|e| BatchError::Io(e.into_error()))?;
+        file_names.push(path.to_string_lossy().to_string());
+    }
+    let mut numbers_and_names = file_names
+        .into_iter()
+        .map(|name| Ok((block_number_from_file(&name)?, name)))
+        .collect::<Result<Vec<_>, BatchError>>()?;
+    numbers_and_names.sort();
+    log::info!(
+        "loading batch {:?}",
+        numbers_and_names
+            .iter()
+            .map(|(_, name)| name)
+            .collect::<Vec<_>>()
+    );
+
+    let mut total_bytes = 0;
+    let mut names = Vec::new();
+    let mut traces = Vec::new();
+    for (idx, (_, name)) in numbers_and_names.iter().enumerate() {
+        total_bytes += metadata(name)?.len() as usize;
+        if total_bytes > max_bytes {
+            log::warn!(
+                "batch {} exceeds {} bytes at {}",
+                batch_dir,
+                max_bytes,
+                name
+            );
+            return Err(BatchError::ExceededMemoryBudget {
+                loaded: idx,
+                skipped: numbers_and_names.len() - idx,
+            });
+        }
+        let buffer = fs::read(name)?;
+        let trace = block_trace_from_slice(&buffer).map_err(|e| BatchError::InvalidTrace {
+            path: name.clone(),
+            reason: e.to_string(),
+        })?;
+        names.push(name.clone());
+        traces.push(trace);
+    }
+    Ok((names, traces))
+}
+
+/// get a block-result from file, skipping the per-step data
+pub fn get_block_trace_lite_from_file<P: AsRef<Path>>(path: P) -> BlockTraceLite {
+    let buffer = fs::read(&path).unwrap();
+
+    serde_json::from_slice::<BlockTraceLite>(&buffer).unwrap_or_else(|e1| {
+        serde_json::from_slice::<BlockTraceLiteJsonRpcResult>(&buffer)
+            .map_err(|e2| {
+                panic!(
+                    "unable to load BlockTraceLite from {:?}, {:?}, {:?}",
+                    path.as_ref(),
+                    e1,
+                    e2
+                )
+            })
+            .unwrap()
+            .result
+    })
+}
+
+/// Parse a block trace from json, accepting both the raw `BlockTrace` and the json-rpc result.
+fn block_trace_from_slice(buf: &[u8]) -> Result<BlockTrace> {
+    serde_json::from_slice::<BlockTrace>(buf).or_else(|e1| {
+        serde_json::from_slice::<BlockTraceJsonRpcResult>(buf)
+            .map(|r| r.result)
+            .map_err(|e2| anyhow::format_err!("unable to load BlockTrace, {:?}, {:?}", e1, e2))
+    })
+}
+
+/// What to do when a line of a newline-delimited json trace stream is malformed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NdjsonErrorPolicy {
+    /// Report the error and stop reading.
+    Abort,
+    /// Report the error and go on with the next line.
+    Continue,
+}
+
+/// Iterator over block traces read from newline-delimited json.
+pub struct NdjsonBlockTraces<R> {
+    reader: R,
+    policy: NdjsonErrorPolicy,
+    line_number: usize,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for NdjsonBlockTraces<R> {
+    type Item = Result<BlockTrace>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            // the line buffer is reused, so huge lines are read without extra copies
+            self.buf.clear();
+            self.line_number += 1;
+            let result = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(_) => {
+                    let line = trim_ascii_whitespace(&self.buf);
+                    if line.is_empty() {
+                        continue;
+                    }
+                    block_trace_from_slice(line)
+                }
+                Err(e) => {
+                    self.done = true;
+                    Err(e.into())
+                }
+            };
+            return Some(result.map_err(|e| {
+                log::error!("failed to read trace at line {}: {}", self.line_number, e);
+                if self.policy == NdjsonErrorPolicy::Abort {
+                    self.done = true;
+                }
+                e.context(format!("line {}", self.line_number))
+            }));
+        }
+        None
+    }
+}
+
+fn trim_ascii_whitespace(buf: &[u8]) -> &[u8] {
+    let start = buf.iter().position(|b| !b.is_ascii_whitespace());
+    let end = buf.iter().rposition(|b| !b.is_ascii_whitespace());
+    match (start, end) {
+        (Some(start), Some(end)) => &buf[start..=end],
+        _ => &[],
+    }
+}
+
+/// Read block traces from newline-delimited json, one `BlockTrace` per line.
+/// Reading stops at the first malformed line.
+pub fn read_block_traces_ndjson(reader: impl BufRead) -> impl Iterator<Item = Result<BlockTrace>> {
+    read_block_traces_ndjson_with_policy(reader, NdjsonErrorPolicy::Abort)
+}
+
+/// Read block traces from newline-delimited json, handling malformed lines per `policy`.
+/// Errors carry the (1-based) line number.
+pub fn read_block_traces_ndjson_with_policy<R: BufRead>(
+    reader: R,
+    policy: NdjsonErrorPolicy,
+) -> NdjsonBlockTraces<R> {
+    NdjsonBlockTraces {
+        reader,
+        policy,
+        line_number: 0,
+        buf: Vec::new(),
+        done: false,
+    }
+}
+
+/// Format of a trace stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// A single json document, either a `BlockTrace` or a json-rpc result.
+    Json,
+    /// Newline-delimited json, one `BlockTrace` per line.
+    Ndjson,
+}
+
+/// Where block traces are read from.
+#[derive(Debug, Clone)]
+pub enum TraceSource {
+    File(PathBuf),
+    Stdin { format: TraceFormat },
+}
+
+impl TraceSource {
+    /// Read all traces from the source. Malformed ndjson lines are handled per `policy`.
+    pub fn load(&self, policy: NdjsonErrorPolicy) -> Result<Vec<BlockTrace>> {
+        match self {
+            Self::File(path) => Ok(vec![block_trace_from_slice(&fs::read(path)?)?]),
+            Self::Stdin {
+                format: TraceFormat::Json,
+            } => {
+                let mut buf = Vec::new();
+                std::io::stdin().lock().read_to_end(&mut buf)?;
+                Ok(vec![block_trace_from_slice(&buf)?])
+            }
+            Self::Stdin {
+                format: TraceFormat::Ndjson,
+            } => read_block_traces_ndjson_with_policy(std::io::stdin().lock(), policy)
+                .filter(|r| policy == NdjsonErrorPolicy::Abort || r.is_ok())
+                .collect(),
+        }
+    }
+}
+
+/// Write a block trace as json without any whitespace.
+pub fn block_trace_to_json_compact(trace: &BlockTrace, writer: impl Write) -> Result<()> {
+    serde_json::to_writer(writer, trace)?;
+    Ok(())
+}
+
+/// Drop the trace data that no circuit reads, i.e. the memory snapshots of the struct logs.
+/// The result is still a valid `BlockTrace` for witness generation.
+pub fn filter_block_trace_for_circuit(trace: &BlockTrace) -> BlockTrace {
+    let mut trace = trace.clone();
+    for exec_step in trace
+        .execution_results
+        .iter_mut()
+        .flat_map(|result| result.exec_steps.iter_mut())
+    {
+        exec_step.memory = None;
+    }
+    trace
+}
+
+/// Canonical byte form of a block trace, produced by [`canonicalize_block_trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalTraceBytes(pub Vec<u8>);
+
+impl AsRef<[u8]> for CanonicalTraceBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Serialize a block trace into a deterministic byte form, so that semantically equal dumps
+/// (e.g. the same block fetched from different geth nodes) are byte-identical.
+///
+/// The canonical form is compact JSON where object keys are sorted, `null` fields are omitted,
+/// `0x`-prefixed hex strings are lowercased and numbers use serde_json's fixed formatting.
+/// Canonicalization never changes the semantic content of the trace: it only normalizes
+/// encoding choices that the `BlockTrace` deserializer already treats as equivalent.
+pub fn canonicalize_block_trace(trace: &BlockTrace) -> CanonicalTraceBytes {
+    let value = serde_json::to_value(trace).expect("BlockTrace is always serializable");
+    let bytes = serde_json::to_vec(&canonicalize_json_value(value))
+        .expect("canonical BlockTrace is always serializable");
+    CanonicalTraceBytes(bytes)
+}
+
+/// Hash of the canonical form of a block trace, see [`canonicalize_block_trace`].
+pub fn canonical_trace_hash(trace: &BlockTrace) -> H256 {
+    H256::from_slice(&Sha256::digest(canonicalize_block_trace(trace)))
+}
+
+fn canonicalize_json_value(value: Value) -> Value {
+    match value {
+        // without the `preserve_order` feature, serde_json keeps object keys sorted
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (canonicalize_hex_str(k), canonicalize_json_value(v)))
+                .collect(),
+        ),
+        Value::Array(values) => {
+            Value::Array(values.into_iter().map(canonicalize_json_value).collect())
+        }
+        Value::String(s) => Value::String(canonicalize_hex_str(s)),
+        v => v,
+    }
+}
+
+fn canonicalize_hex_str(s: String) -> String {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) if hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            format!("0x{}", hex.to_ascii_lowercase())
+        }
+        _ => s,
+    }
+}
+
 pub fn read_env_var<T: Clone + FromStr>(var_name: &'static str, default: T) -> T {
     std::env::var(var_name)
         .map(|s| s.parse::<T>().unwrap_or_else(|_| default.clone()))
@@ -179,3 +880,81 @@ pub fn metric_of_witness_block(block: &witness::Block<Fr>) -> BatchMetric {
         num_step: block.txs.iter().map(|tx| tx.steps.len()).sum::<usize>(),
     }
 }
+
+/// Cost of a batch measured on the traces alone, without building the witness block.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceCostReport {
+    pub num_block: usize,
+    pub num_tx: usize,
+    pub num_step: usize,
+    pub num_calldata_bytes: usize,
+    pub num_proof_nodes: usize,
+    pub opcode_counts: HashMap<OpcodeId, usize>,
+}
+
+pub fn trace_cost_report(block_traces: &[BlockTrace]) -> TraceCostReport {
+    let mut report = TraceCostReport {
+        num_block: block_traces.len(),
+        ..Default::default()
+    };
+    for block_trace in block_traces {
+        report.num_tx += block_trace.transactions.len();
+        report.num_calldata_bytes += block_trace
+            .transactions
+            .iter()
+            .map(|tx| tx.data.len())
+            .sum::<usize>();
+        let storage_trace = &block_trace.storage_trace;
+        report.num_proof_nodes += storage_trace
+            .proofs
+            .iter()
+            .flat_map(|proofs| proofs.values())
+            .chain(
+                storage_trace
+                    .storage_proofs
+                    .values()
+                    .flat_map(|slots| slots.values()),
+            )
+            .map(Vec::len)
+            .sum::<usize>()
+            + storage_trace.deletion_proofs.len();
+        for step in block_trace
+            .execution_results
+            .iter()
+            .flat_map(|result| result.exec_steps.iter())
+        {
+            report.num_step += 1;
+            *report.opcode_counts.entry(step.op).or_insert(0) += 1;
+        }
+    }
+    report
+}
+
+/// Same as [`trace_cost_report`], from lite traces.
+pub fn trace_cost_report_lite(block_traces: &[BlockTraceLite]) -> TraceCostReport {
+    let mut report = TraceCostReport {
+        num_block: block_traces.len(),
+        ..Default::default()
+    };
+    for block_trace in block_traces {
+        report.num_tx += block_trace.transactions.len();
+        report.num_calldata_bytes += block_trace
+            .transactions
+            .iter()
+            .map(|tx| tx.data.len())
+            .sum::<usize>();
+        let storage_trace = &block_trace.storage_trace;
+        report.num_proof_nodes += storage_trace.account_proof_nodes
+            + storage_trace.storage_proof_nodes
+            + storage_trace.deletion_proof_nodes;
+        report.num_step += block_trace.num_steps();
+        for (op, count) in block_trace
+            .execution_results
+            .iter()
+            .flat_map(|result| result.opcode_counts.iter())
+        {
+            *report.opcode_counts.entry(*op).or_insert(0) += count;
+        }
+    }
+    report
+}
@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 use std::io::Cursor;
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::circuit::{TargetCircuit, AGG_DEGREE, DEGREE};
-use crate::io::load_instances;
+use crate::io::{load_instances, serialize_vk, vk_fingerprint};
 use crate::prover::{AggCircuitProof, TargetCircuitProof};
 use crate::utils::{load_params, DEFAULT_SERDE_FORMAT};
 use anyhow::anyhow;
@@ -15,16 +16,49 @@ use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
 use halo2_proofs::poly::kzg::strategy::AccumulatorStrategy;
 use halo2_proofs::poly::VerificationStrategy;
 use halo2_proofs::transcript::TranscriptReadBuffer;
+use snark_verifier::loader::evm::{encode_calldata, Address, ExecutorBuilder};
 use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
 use snark_verifier_sdk::evm::evm_verify;
 use snark_verifier_sdk::halo2::aggregation::AggregationCircuit;
 use snark_verifier_sdk::halo2::verify_snark_shplonk;
 
+mod failures;
+mod replay;
+mod vk_registry;
+use failures::FailureLog;
+pub use failures::{FailureSummary, VerifyError, DEFAULT_RECENT_FAILURES_CAPACITY};
+pub use replay::{
+    sidecar_path, write_sidecar, IntegrityError, IntegrityLayer, ReplayOptions, ReplayReport,
+};
+use vk_registry::VkRegistry;
+pub use vk_registry::{VkDirScan, VkDirWatcher, VK_QUARANTINE_DIR};
+
 pub struct Verifier {
     params: ParamsKZG<Bn256>,
     agg_params: ParamsKZG<Bn256>,
-    agg_vk: Option<VerifyingKey<G1Affine>>,
+    /// The aggregation vk, either given at construction or lazily read from `agg_vk_path`.
+    agg_vk: Arc<RwLock<Option<VerifyingKey<G1Affine>>>>,
+    agg_vk_path: Option<String>,
     target_circuit_vks: HashMap<String, VerifyingKey<G1Affine>>,
+    /// Fingerprints of `target_circuit_vks`, by circuit name.
+    vk_fingerprints: HashMap<String, String>,
+    /// Vks hot-reloaded from a vk directory, keyed by circuit name and fingerprint.
+    vk_registry: Arc<RwLock<VkRegistry>>,
+    /// Recent target circuit verification failures.
+    failures: Mutex<FailureLog>,
+}
+
+fn keygen_target_circuit_vk<C: TargetCircuit>(params: &ParamsKZG<Bn256>) -> VerifyingKey<G1Affine> {
+    keygen_vk(params, &C::dummy_inner_circuit())
+        .unwrap_or_else(|_| panic!("failed to generate {} vk", C::name()))
+}
+
+fn read_agg_vk(raw_agg_vk: &[u8]) -> VerifyingKey<G1Affine> {
+    VerifyingKey::<G1Affine>::read::<_, AggregationCircuit>(
+        &mut Cursor::new(raw_agg_vk),
+        halo2_proofs::SerdeFormat::Processed,
+    )
+    .unwrap()
 }
 
 impl Verifier {
@@ -33,19 +67,17 @@ impl Verifier {
         agg_params: ParamsKZG<Bn256>,
         raw_agg_vk: Option<Vec<u8>>,
     ) -> Self {
-        let agg_vk = raw_agg_vk.as_ref().map(|k| {
-            VerifyingKey::<G1Affine>::read::<_, AggregationCircuit>(
-                &mut Cursor::new(&k),
-                halo2_proofs::SerdeFormat::Processed,
-            )
-            .unwrap()
-        });
+        let agg_vk = raw_agg_vk.as_deref().map(read_agg_vk);
 
         Self {
             params,
             agg_params,
-            agg_vk,
+            agg_vk: Arc::new(RwLock::new(agg_vk)),
+            agg_vk_path: None,
             target_circuit_vks: Default::default(),
+            vk_fingerprints: Default::default(),
+            vk_registry: Default::default(),
+            failures: Default::default(),
         }
     }
 
@@ -65,13 +97,48 @@ impl Verifier {
         Self::from_params(params, agg_params, agg_vk)
     }
 
+    /// Build a verifier whose aggregation vk is read from `agg_vk_path` on first use,
+    /// and cached for all subsequent verifications.
+    pub fn from_params_and_agg_vk_path(
+        params: ParamsKZG<Bn256>,
+        agg_params: ParamsKZG<Bn256>,
+        agg_vk_path: &str,
+    ) -> Self {
+        let mut verifier = Self::new(params, agg_params, None);
+        verifier.agg_vk_path = Some(agg_vk_path.to_string());
+        verifier
+    }
+
+    pub fn from_fpath_with_agg_vk_path(params_path: &str, agg_vk_path: &str) -> Self {
+        let params =
+            load_params(params_path, *DEGREE, DEFAULT_SERDE_FORMAT).expect("failed to init params");
+        let agg_params = load_params(params_path, *AGG_DEGREE, DEFAULT_SERDE_FORMAT)
+            .expect("failed to init params");
+        Self::from_params_and_agg_vk_path(params, agg_params, agg_vk_path)
+    }
+
+    fn agg_vk(&self) -> anyhow::Result<VerifyingKey<G1Affine>> {
+        if let Some(vk) = self.agg_vk.read().unwrap().as_ref() {
+            return Ok(vk.clone());
+        }
+
+        let mut agg_vk = self.agg_vk.write().unwrap();
+        // another thread may have loaded the vk while we were waiting for the lock
+        if agg_vk.is_none() {
+            let path = match &self.agg_vk_path {
+                Some(path) => path,
+                None => panic!("aggregation verification key is not found"),
+            };
+            log::info!("load aggregation vk from {}", path);
+            *agg_vk = Some(read_agg_vk(&std::fs::read(path)?));
+        }
+        Ok(agg_vk.as_ref().unwrap().clone())
+    }
+
     pub fn verify_agg_circuit_proof(&self, proof: AggCircuitProof) -> anyhow::Result<bool> {
         let mut transcript = TranscriptReadBuffer::<_, G1Affine, _>::init(proof.proof.as_slice());
 
-        let vk = match self.agg_vk.clone() {
-            Some(p) => p,
-            None => panic!("aggregation verification key is not found"),
-        };
+        let vk = self.agg_vk()?;
 
         // deserialize instances
         let verify_circuit_instance: Vec<Vec<Vec<Fr>>> = {
@@ -96,22 +163,106 @@ impl Verifier {
         ))
     }
 
+    /// Deploy the evm verifier `deployment_code` into an in-memory revm and call it with the
+    /// calldata of `proof`, i.e. its instances followed by the proof bytes.
+    ///
+    /// The generated verifier returns nothing and reverts on a bad proof, so the result is
+    /// whether the call succeeded.
+    pub fn verify_with_revm(
+        &self,
+        proof: &AggCircuitProof,
+        deployment_code: &[u8],
+    ) -> anyhow::Result<bool> {
+        let instances = load_instances(&proof.instance)
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("aggregation proof without instance"))?;
+        let calldata = encode_calldata(&instances, &proof.proof);
+
+        let mut evm = ExecutorBuilder::default()
+            .with_gas_limit(u64::MAX.into())
+            .build();
+        let caller = Address::from_low_u64_be(0xfe);
+        let verifier = evm
+            .deploy(caller, deployment_code.to_vec().into(), 0.into())
+            .address
+            .ok_or_else(|| anyhow!("failed to deploy the evm verifier"))?;
+        let result = evm.call_raw(caller, verifier, calldata.into(), 0.into());
+        log::info!("evm verification gas used: {}", result.gas_used);
+        Ok(!result.reverted)
+    }
+
+    /// Fingerprint of the built-in vk of circuit `C`, generated on first use.
+    pub fn target_circuit_vk_fingerprint<C: TargetCircuit>(&mut self) -> String {
+        if let Some(fingerprint) = self.vk_fingerprints.get(&C::name()) {
+            return fingerprint.clone();
+        }
+        let vk = self
+            .target_circuit_vks
+            .entry(C::name())
+            .or_insert_with(|| keygen_target_circuit_vk::<C>(&self.params));
+        let fingerprint = vk_fingerprint(&serialize_vk(vk));
+        self.vk_fingerprints.insert(C::name(), fingerprint.clone());
+        fingerprint
+    }
+
     pub fn verify_target_circuit_proof<C: TargetCircuit>(
         &mut self,
         proof: &TargetCircuitProof,
     ) -> anyhow::Result<()> {
+        let circuit = C::name();
+        // checked before picking a vk, so a proof of another circuit never triggers a keygen
+        if proof.name != circuit {
+            return Err(self.record_failure(VerifyError::CircuitMismatch {
+                circuit,
+                proof_circuit: proof.name.clone(),
+            }));
+        }
+        // a vk registered for the proof's circuit version takes precedence over the built-in one
+        let proof_vk_fingerprint = vk_fingerprint(&proof.vk);
+        let registered = self
+            .vk_registry
+            .read()
+            .unwrap()
+            .get(&circuit, &proof_vk_fingerprint);
+        // errors carry the fingerprint of the vk verified against, whatever vk the proof claims
+        let fingerprint = match registered {
+            // registered vks are checked to match the fingerprint they are keyed by
+            Some(_) => proof_vk_fingerprint,
+            None => self.target_circuit_vk_fingerprint::<C>(),
+        };
+        let vk = match &registered {
+            Some(vk) => vk.as_ref(),
+            None => &self.target_circuit_vks[&circuit],
+        };
         let verifier_params = self.params.verifier_params();
-        let vk = self.target_circuit_vks.entry(C::name()).or_insert_with(|| {
-            let circuit = C::dummy_inner_circuit();
-            keygen_vk(&self.params, &circuit)
-                .unwrap_or_else(|_| panic!("failed to generate {} vk", C::name()))
-        });
-        if verify_snark_shplonk::<C::Inner>(verifier_params, proof.snark.clone(), vk) {
-            Ok(())
+
+        let error = if proof.snark.instances.len() != vk.cs().num_instance_columns() {
+            Some(VerifyError::InstanceShape {
+                circuit,
+                vk_fingerprint: fingerprint,
+                expected: vk.cs().num_instance_columns(),
+                got: proof.snark.instances.len(),
+            })
+        } else if !verify_snark_shplonk::<C::Inner>(verifier_params, proof.snark.clone(), vk) {
+            Some(VerifyError::Snark {
+                circuit,
+                vk_fingerprint: fingerprint,
+            })
         } else {
-            Err(anyhow!("snark verification failed".to_string()))
+            None
+        };
+
+        match error {
+            Some(error) => Err(self.record_failure(error)),
+            None => Ok(()),
         }
     }
+
+    fn record_failure(&self, error: VerifyError) -> anyhow::Error {
+        self.failures.lock().unwrap().record(&error);
+        error.into()
+    }
 }
 
 pub struct EvmVerifier {
@@ -0,0 +1,204 @@
+//! Verification errors, with fingerprints to group failures by root cause across hosts.
+
+use super::Verifier;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Number of failures kept by [`Verifier::recent_failures`] unless configured otherwise.
+pub const DEFAULT_RECENT_FAILURES_CAPACITY: usize = 64;
+
+/// A target circuit proof failed verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The proof is for another circuit.
+    CircuitMismatch {
+        circuit: String,
+        proof_circuit: String,
+    },
+    /// The number of instance columns differs from the vk's.
+    InstanceShape {
+        circuit: String,
+        vk_fingerprint: String,
+        expected: usize,
+        got: usize,
+    },
+    /// The snark does not verify against the vk.
+    Snark {
+        circuit: String,
+        vk_fingerprint: String,
+    },
+}
+
+impl VerifyError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::CircuitMismatch { .. } => "circuit_mismatch",
+            Self::InstanceShape { .. } => "instance_shape",
+            Self::Snark { .. } => "snark",
+        }
+    }
+
+    /// The verification step which failed.
+    pub fn stage(&self) -> &'static str {
+        match self {
+            Self::CircuitMismatch { .. } | Self::InstanceShape { .. } => "precheck",
+            Self::Snark { .. } => "snark",
+        }
+    }
+
+    pub fn circuit(&self) -> &str {
+        match self {
+            Self::CircuitMismatch { circuit, .. }
+            | Self::InstanceShape { circuit, .. }
+            | Self::Snark { circuit, .. } => circuit,
+        }
+    }
+
+    /// Fingerprint of the vk the proof was verified against, none for a proof of another
+    /// circuit, which is refused before a vk is picked.
+    pub fn vk_fingerprint(&self) -> Option<&str> {
+        match self {
+            Self::CircuitMismatch { .. } => None,
+            Self::InstanceShape { vk_fingerprint, .. } | Self::Snark { vk_fingerprint, .. } => {
+                Some(vk_fingerprint)
+            }
+        }
+    }
+
+    /// Stable hash of the root cause: kind, stage, circuit, vk fingerprint and, for shape errors,
+    /// the expected and actual shape. Proof bytes and instance values are left out, so the same
+    /// failure has the same fingerprint on every host and nothing of the proof is leaked.
+    pub fn fingerprint(&self) -> [u8; 16] {
+        let detail = match self {
+            Self::CircuitMismatch { proof_circuit, .. } => proof_circuit.clone(),
+            Self::InstanceShape { expected, got, .. } => format!("{}/{}", expected, got),
+            Self::Snark { .. } => String::new(),
+        };
+        let input = [
+            self.kind(),
+            self.stage(),
+            self.circuit(),
+            self.vk_fingerprint().unwrap_or_default(),
+            &detail,
+        ]
+        .join("\n");
+        Sha256::digest(input.as_bytes())[..16].try_into().unwrap()
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CircuitMismatch {
+                circuit,
+                proof_circuit,
+                ..
+            } => write!(
+                f,
+                "proof of circuit {}, expected {}",
+                proof_circuit, circuit
+            ),
+            Self::InstanceShape {
+                circuit,
+                expected,
+                got,
+                ..
+            } => write!(
+                f,
+                "{} proof with {} instance columns, expected {}",
+                circuit, got, expected
+            ),
+            Self::Snark {
+                circuit,
+                vk_fingerprint,
+            } => write!(
+                f,
+                "{} snark verification failed with vk {}",
+                circuit, vk_fingerprint
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// A recorded verification failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureSummary {
+    pub fingerprint: [u8; 16],
+    pub error: VerifyError,
+}
+
+/// Recent failures, capped, and the number of failures per fingerprint since start.
+#[derive(Debug)]
+pub(crate) struct FailureLog {
+    capacity: usize,
+    recent: VecDeque<FailureSummary>,
+    counts: HashMap<[u8; 16], u64>,
+}
+
+impl Default for FailureLog {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_RECENT_FAILURES_CAPACITY,
+            recent: VecDeque::new(),
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl FailureLog {
+    pub(crate) fn record(&mut self, error: &VerifyError) {
+        let fingerprint = error.fingerprint();
+        let count = self.counts.entry(fingerprint).or_default();
+        *count += 1;
+        log::warn!(
+            "verify failure fingerprint={} kind={} stage={} circuit={} vk={} count={}",
+            hex::encode(fingerprint),
+            error.kind(),
+            error.stage(),
+            error.circuit(),
+            error.vk_fingerprint().unwrap_or("-"),
+            count
+        );
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(FailureSummary {
+            fingerprint,
+            error: error.clone(),
+        });
+    }
+}
+
+impl Verifier {
+    /// The most recent verification failures, oldest first.
+    pub fn recent_failures(&self) -> Vec<FailureSummary> {
+        self.failures
+            .lock()
+            .unwrap()
+            .recent
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Number of verification failures per fingerprint since the verifier was built.
+    pub fn failure_counts(&self) -> HashMap<[u8; 16], u64> {
+        self.failures.lock().unwrap().counts.clone()
+    }
+
+    /// Keep the `capacity` most recent failures, dropping the older ones.
+    pub fn set_recent_failures_capacity(&self, capacity: usize) {
+        let mut failures = self.failures.lock().unwrap();
+        failures.capacity = capacity;
+        while failures.recent.len() > capacity {
+            failures.recent.pop_front();
+        }
+    }
+}
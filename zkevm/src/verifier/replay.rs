@@ -0,0 +1,229 @@
+//! Cheap integrity checks and sampled replay of archived target circuit proofs.
+
+use super::Verifier;
+use crate::circuit::TargetCircuit;
+use crate::io::vk_fingerprint;
+use crate::prover::TargetCircuitProof;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// The layer of an archived proof at which a check failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityLayer {
+    /// The `.sha256` sidecar is missing or does not match the proof file.
+    ContentHash,
+    /// The file is not a `TargetCircuitProof`.
+    Schema,
+    /// Circuit name or block counts are inconsistent.
+    Metadata,
+    /// The vk is neither the verifier's vk of the circuit nor a registered one.
+    VkFingerprint,
+    /// The number of public inputs does not match the circuit.
+    InstanceShape,
+    /// Full snark verification failed.
+    Snark,
+}
+
+/// An archived proof failed a check.
+#[derive(Debug, Clone)]
+pub struct IntegrityError {
+    pub file: PathBuf,
+    pub layer: IntegrityLayer,
+    pub reason: String,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} check failed for {:?}: {}",
+            self.layer, self.file, self.reason
+        )
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+/// Path of the sha256 sidecar of a proof file, i.e. `<proof file>.sha256`.
+pub fn sidecar_path(proof_path: &Path) -> PathBuf {
+    let mut path = proof_path.as_os_str().to_owned();
+    path.push(".sha256");
+    PathBuf::from(path)
+}
+
+/// Write the sha256 sidecar of a proof file.
+pub fn write_sidecar(proof_path: &Path) -> anyhow::Result<()> {
+    let digest = Sha256::digest(std::fs::read(proof_path)?);
+    std::fs::write(sidecar_path(proof_path), hex::encode(digest))?;
+    Ok(())
+}
+
+/// Options of [`Verifier::replay_archive`].
+#[derive(Debug, Clone, Default)]
+pub struct ReplayOptions {
+    /// Fraction of the proofs which also get full snark verification.
+    pub full_verification_fraction: f64,
+    pub seed: u64,
+}
+
+impl ReplayOptions {
+    /// Fully verify a pseudo-random `fraction` of the proofs, selected deterministically
+    /// from `seed` and the file paths.
+    pub fn sample_full_verification(fraction: f64, seed: u64) -> Self {
+        Self {
+            full_verification_fraction: fraction,
+            seed,
+        }
+    }
+
+    /// Whether the proof at `path` is selected for full verification.
+    pub fn is_sampled(&self, path: &Path) -> bool {
+        let digest = Sha256::new()
+            .chain_update(self.seed.to_le_bytes())
+            .chain_update(path.to_string_lossy().as_bytes())
+            .finalize();
+        let x = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        (x as f64) < self.full_verification_fraction * (u64::MAX as f64)
+    }
+}
+
+/// Result of replaying an archive.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub checked: usize,
+    /// The proofs selected for full verification, kept for audit.
+    pub sampled: Vec<PathBuf>,
+    pub failures: Vec<IntegrityError>,
+}
+
+impl Verifier {
+    /// Run the cheap checks on an archived proof file: content hash sidecar, schema,
+    /// metadata, vk fingerprint and instance shape. The snark itself is not verified.
+    pub fn integrity_check<C: TargetCircuit>(&mut self, proof_path: &Path) -> anyhow::Result<()> {
+        self.check_archived_proof::<C>(proof_path)?;
+        Ok(())
+    }
+
+    fn check_archived_proof<C: TargetCircuit>(
+        &mut self,
+        proof_path: &Path,
+    ) -> Result<TargetCircuitProof, IntegrityError> {
+        let fail = |layer, reason: String| IntegrityError {
+            file: proof_path.to_path_buf(),
+            layer,
+            reason,
+        };
+
+        let bytes =
+            std::fs::read(proof_path).map_err(|e| fail(IntegrityLayer::Schema, e.to_string()))?;
+        let sidecar = std::fs::read_to_string(sidecar_path(proof_path))
+            .map_err(|e| fail(IntegrityLayer::ContentHash, e.to_string()))?;
+        let digest = hex::encode(Sha256::digest(&bytes));
+        if sidecar.trim() != digest {
+            return Err(fail(
+                IntegrityLayer::ContentHash,
+                format!("sidecar {}, file {}", sidecar.trim(), digest),
+            ));
+        }
+
+        let proof = serde_json::from_slice::<TargetCircuitProof>(&bytes)
+            .map_err(|e| fail(IntegrityLayer::Schema, e.to_string()))?;
+
+        if proof.name != C::name() {
+            return Err(fail(
+                IntegrityLayer::Metadata,
+                format!("proof of circuit {}, expected {}", proof.name, C::name()),
+            ));
+        }
+        if proof.num_of_proved_blocks > proof.total_num_of_blocks {
+            return Err(fail(
+                IntegrityLayer::Metadata,
+                format!(
+                    "{} blocks proved out of {}",
+                    proof.num_of_proved_blocks, proof.total_num_of_blocks
+                ),
+            ));
+        }
+
+        // the vk must be the built-in one, or one registered from a vk directory
+        let fingerprint = vk_fingerprint(&proof.vk);
+        let registered = self
+            .vk_registry
+            .read()
+            .unwrap()
+            .get(&C::name(), &fingerprint)
+            .is_some();
+        if !registered {
+            let expected = self.target_circuit_vk_fingerprint::<C>();
+            if fingerprint != expected {
+                return Err(fail(
+                    IntegrityLayer::VkFingerprint,
+                    format!("vk {}, expected {}", fingerprint, expected),
+                ));
+            }
+        }
+
+        let num_instances: usize = proof.snark.instances.iter().map(Vec::len).sum();
+        if num_instances != C::public_input_len() {
+            return Err(fail(
+                IntegrityLayer::InstanceShape,
+                format!(
+                    "{} public inputs, expected {}",
+                    num_instances,
+                    C::public_input_len()
+                ),
+            ));
+        }
+
+        Ok(proof)
+    }
+
+    /// Integrity check all `proof_paths`, fully verifying the subset sampled by `options`.
+    pub fn replay_archive<C: TargetCircuit>(
+        &mut self,
+        proof_paths: &[PathBuf],
+        options: &ReplayOptions,
+    ) -> ReplayReport {
+        let mut report = ReplayReport::default();
+        for path in proof_paths {
+            report.checked += 1;
+            let proof = match self.check_archived_proof::<C>(path) {
+                Ok(proof) => proof,
+                Err(e) => {
+                    log::error!("{}", e);
+                    report.failures.push(e);
+                    continue;
+                }
+            };
+            if !options.is_sampled(path) {
+                continue;
+            }
+            report.sampled.push(path.clone());
+            // malformed proof bytes may panic in the transcript instead of failing
+            let verified = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.verify_target_circuit_proof::<C>(&proof)
+            }));
+            let reason = match verified {
+                Ok(Ok(())) => continue,
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "panicked while verifying".to_string(),
+            };
+            let e = IntegrityError {
+                file: path.clone(),
+                layer: IntegrityLayer::Snark,
+                reason,
+            };
+            log::error!("{}", e);
+            report.failures.push(e);
+        }
+        log::info!(
+            "replayed {} proofs, {} fully verified, {} failures",
+            report.checked,
+            report.sampled.len(),
+            report.failures.len()
+        );
+        report
+    }
+}
@@ -0,0 +1,233 @@
+//! Versioned registry of target circuit vks, hot-reloaded from a directory of
+//! `{name}.{fingerprint}.vkey` files.
+
+use super::Verifier;
+use crate::circuit::TargetCircuit;
+use crate::io::vk_fingerprint;
+use halo2_proofs::halo2curves::bn256::G1Affine;
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::SerdeFormat;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Sub directory of the vk directory where malformed vk files are moved.
+pub const VK_QUARANTINE_DIR: &str = "quarantine";
+
+/// Vks keyed by circuit name and fingerprint. Vks are shared with in-flight verifications
+/// through `Arc`, so a retired vk lives until the last of them is done.
+#[derive(Debug, Default)]
+pub(crate) struct VkRegistry {
+    vks: HashMap<(String, String), Arc<VerifyingKey<G1Affine>>>,
+}
+
+impl VkRegistry {
+    pub(crate) fn get(&self, name: &str, fingerprint: &str) -> Option<Arc<VerifyingKey<G1Affine>>> {
+        self.vks
+            .get(&(name.to_string(), fingerprint.to_string()))
+            .cloned()
+    }
+}
+
+/// Changes applied to the registry by one scan of a vk directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VkDirScan {
+    /// Fingerprints of the vks added to the registry.
+    pub added: Vec<String>,
+    /// Fingerprints of the vks whose file was removed.
+    pub retired: Vec<String>,
+    /// Malformed vk files, moved to the quarantine directory.
+    pub quarantined: Vec<PathBuf>,
+}
+
+/// Background thread polling a vk directory, stopped when dropped.
+#[derive(Debug)]
+pub struct VkDirWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl VkDirWatcher {
+    pub fn stop(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for VkDirWatcher {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+/// Parse the fingerprint out of a `{name}.{fingerprint}.vkey` file name.
+fn vk_file_fingerprint(path: &Path, name: &str) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let fingerprint = file_name
+        .strip_prefix(name)?
+        .strip_prefix('.')?
+        .strip_suffix(".vkey")?;
+    (!fingerprint.is_empty() && !fingerprint.contains('.')).then(|| fingerprint.to_string())
+}
+
+/// Read a vk file, checking that its content matches the fingerprint of its name.
+fn read_vk_file_bytes(path: &Path, fingerprint: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    let actual = vk_fingerprint(&bytes);
+    if actual != fingerprint {
+        anyhow::bail!("fingerprint of the content is {}", actual);
+    }
+    Ok(bytes)
+}
+
+fn read_vk_file<C: TargetCircuit>(
+    path: &Path,
+    fingerprint: &str,
+) -> anyhow::Result<VerifyingKey<G1Affine>> {
+    let bytes = read_vk_file_bytes(path, fingerprint)?;
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        VerifyingKey::<G1Affine>::read::<_, C::Inner>(
+            &mut Cursor::new(&bytes),
+            SerdeFormat::Processed,
+        )
+    }))
+    .map_err(|_| anyhow::anyhow!("vk parsing panicked"))?
+    .map_err(Into::into)
+}
+
+fn quarantine(dir: &Path, path: &Path) -> anyhow::Result<PathBuf> {
+    let quarantine_dir = dir.join(VK_QUARANTINE_DIR);
+    std::fs::create_dir_all(&quarantine_dir)?;
+    let target = quarantine_dir.join(path.file_name().unwrap_or_default());
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Bring the vks of circuit `C` in the registry in line with the files of `dir`. The content of
+/// registered vk files is checked again on every scan, and a vk whose file no longer matches
+/// is retired.
+fn scan_vk_dir<C: TargetCircuit>(
+    registry: &RwLock<VkRegistry>,
+    dir: &Path,
+) -> anyhow::Result<VkDirScan> {
+    let name = C::name();
+    let mut scan = VkDirScan::default();
+    let mut on_disk = vec![];
+
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let fingerprint = match vk_file_fingerprint(&path, &name) {
+            Some(fingerprint) => fingerprint,
+            None => continue,
+        };
+        let checked = if registry.read().unwrap().get(&name, &fingerprint).is_some() {
+            // the file may have been rewritten since it was registered
+            read_vk_file_bytes(&path, &fingerprint).map(|_| None)
+        } else {
+            read_vk_file::<C>(&path, &fingerprint).map(Some)
+        };
+        match checked {
+            Ok(None) => on_disk.push(fingerprint),
+            Ok(Some(vk)) => {
+                log::info!("vk registry: add {} vk {}", name, fingerprint);
+                registry
+                    .write()
+                    .unwrap()
+                    .vks
+                    .insert((name.clone(), fingerprint.clone()), Arc::new(vk));
+                on_disk.push(fingerprint.clone());
+                scan.added.push(fingerprint);
+            }
+            Err(e) => {
+                let target = quarantine(dir, &path)?;
+                log::error!(
+                    "vk registry: quarantine malformed vk file {:?} to {:?}: {}",
+                    path,
+                    target,
+                    e
+                );
+                scan.quarantined.push(target);
+            }
+        }
+    }
+
+    let mut registry = registry.write().unwrap();
+    registry.vks.retain(|(vk_name, fingerprint), _| {
+        if *vk_name != name || on_disk.contains(fingerprint) {
+            return true;
+        }
+        log::info!("vk registry: retire {} vk {}", name, fingerprint);
+        scan.retired.push(fingerprint.clone());
+        false
+    });
+    Ok(scan)
+}
+
+impl Verifier {
+    /// Scan `dir` once for `{name}.{fingerprint}.vkey` files of circuit `C`: register new
+    /// vks, retire those whose file is gone and quarantine malformed ones.
+    pub fn scan_vk_dir<C: TargetCircuit>(
+        &self,
+        dir: impl AsRef<Path>,
+    ) -> anyhow::Result<VkDirScan> {
+        scan_vk_dir::<C>(&self.vk_registry, dir.as_ref())
+    }
+
+    /// Rescan `dir` every `poll_interval` in a background thread, see [`Self::scan_vk_dir`].
+    /// Proofs whose vk fingerprint is registered are verified against the registered vk.
+    pub fn watch_vk_dir<C: TargetCircuit + 'static>(
+        &self,
+        dir: impl AsRef<Path>,
+        poll_interval: Duration,
+    ) -> VkDirWatcher {
+        let dir = dir.as_ref().to_path_buf();
+        let registry = self.vk_registry.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Err(e) = scan_vk_dir::<C>(&registry, &dir) {
+                        log::error!("vk registry: failed to scan {:?}: {}", dir, e);
+                    }
+                    std::thread::park_timeout(poll_interval);
+                }
+            })
+        };
+        VkDirWatcher {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Fingerprints of the vks of circuit `C` in the registry.
+    pub fn registered_vk_fingerprints<C: TargetCircuit>(&self) -> Vec<String> {
+        let name = C::name();
+        let mut fingerprints: Vec<_> = self
+            .vk_registry
+            .read()
+            .unwrap()
+            .vks
+            .keys()
+            .filter(|(vk_name, _)| *vk_name == name)
+            .map(|(_, fingerprint)| fingerprint.clone())
+            .collect();
+        fingerprints.sort();
+        fingerprints
+    }
+}
@@ -6,11 +6,16 @@ use zkevm::{
     circuit::{SuperCircuit, TargetCircuit, DEGREE},
     io::serialize_vk,
     prover::Prover,
-    utils::{load_or_create_params, load_params},
+    utils::{
+        create_params, create_params_partial, load_or_create_params, load_params,
+        params_generation_status, params_path_for_degree, ParamsGenerationStatus,
+    },
 };
 
 mod test_util;
-use test_util::{init, load_block_traces_for_test, PARAMS_DIR, SEED_PATH};
+use test_util::{
+    assert_constraint_summary_golden, init, load_block_traces_for_test, PARAMS_DIR, SEED_PATH,
+};
 
 use once_cell::sync::Lazy;
 use zkevm::utils::read_env_var;
@@ -42,6 +47,133 @@ fn test_load_params() {
     .unwrap();
 }
 
+#[test]
+fn test_params_path_for_degree() {
+    assert_eq!(
+        params_path_for_degree(std::path::Path::new("/tmp/params"), 20),
+        std::path::PathBuf::from("/tmp/params/params20")
+    );
+}
+
+#[test]
+fn test_resume_params_generation() {
+    use halo2_proofs::halo2curves::{bn256::Fr, FieldExt};
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use std::io::Write;
+
+    init();
+    let degree = 8;
+    let total_points = 2 << degree;
+    let dir = std::env::temp_dir().join("zkevm_test_resume_params");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let full_path = dir.join("full").to_str().unwrap().to_string();
+    let resumed_path = dir.join("resumed").to_str().unwrap().to_string();
+
+    assert_eq!(
+        params_generation_status(&full_path),
+        ParamsGenerationStatus::Missing
+    );
+    create_params(&full_path, degree).unwrap();
+    assert_eq!(
+        params_generation_status(&full_path),
+        ParamsGenerationStatus::Complete
+    );
+
+    // interrupt after 100 points, with a torn write after the last marker
+    let status = create_params_partial(&resumed_path, degree, 100).unwrap();
+    assert_eq!(
+        status,
+        ParamsGenerationStatus::Partial {
+            points_written: 100,
+            total_points
+        }
+    );
+    assert_eq!(params_generation_status(&resumed_path), status);
+    let partial_path = format!("{resumed_path}.partial");
+    assert!(load_params(&partial_path, degree, SerdeFormat::RawBytesUnchecked).is_err());
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&partial_path)
+        .unwrap()
+        .write_all(&[0xff; 50])
+        .unwrap();
+
+    create_params(&resumed_path, degree).unwrap();
+    assert!(!std::path::Path::new(&partial_path).exists());
+
+    let full = std::fs::read(&full_path).unwrap();
+    assert_eq!(full, std::fs::read(&resumed_path).unwrap());
+
+    let mut seed = [0u8; 64];
+    seed[..32].copy_from_slice(b"bb4b94a1bbef58c4b5fcda6c900629b5");
+    let mut expected = Vec::new();
+    ParamsKZG::<halo2_proofs::halo2curves::bn256::Bn256>::unsafe_setup_with_s(
+        degree as u32,
+        Fr::from_bytes_wide(&seed),
+    )
+    .write_custom(&mut expected, SerdeFormat::RawBytesUnchecked)
+    .unwrap();
+    assert_eq!(full, expected);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_seed_on_read_only_dir() {
+    use std::os::unix::fs::PermissionsExt;
+    use zkevm::utils::{create_seed, is_dir_writable, resolve_seed, SeedUnavailable};
+
+    init();
+    let dir = std::env::temp_dir().join("zkevm_test_read_only_seed");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("with_seed")).unwrap();
+    std::fs::create_dir_all(dir.join("without_seed")).unwrap();
+    let seed_path = dir.join("with_seed/seed");
+    let seed = create_seed(seed_path.to_str().unwrap()).unwrap();
+    let missing_path = dir.join("without_seed/seed");
+    let set_read_only = |read_only: bool| {
+        let mode = if read_only { 0o555 } else { 0o755 };
+        for sub_dir in ["with_seed", "without_seed"] {
+            std::fs::set_permissions(dir.join(sub_dir), std::fs::Permissions::from_mode(mode))
+                .unwrap();
+        }
+    };
+    set_read_only(true);
+
+    // an existing seed is read without any write
+    assert_eq!(
+        resolve_seed(seed_path.to_str().unwrap(), None).unwrap(),
+        seed
+    );
+    // a seed from the environment never touches the disk
+    let env_seed = resolve_seed(
+        missing_path.to_str().unwrap(),
+        Some("0x000102030405060708090a0b0c0d0e0f"),
+    )
+    .unwrap();
+    assert_eq!(env_seed, core::array::from_fn(|i| i as u8));
+    assert!(!missing_path.exists());
+
+    // root ignores the permission bits, so read-only can't be simulated
+    if is_dir_writable(&dir.join("without_seed")) {
+        log::warn!("directory permissions are not enforced, skip the read-only case");
+    } else {
+        let err = resolve_seed(missing_path.to_str().unwrap(), None).unwrap_err();
+        let err = err.downcast_ref::<SeedUnavailable>().unwrap();
+        assert_eq!(err.seed_path, missing_path);
+        assert!(err.to_string().contains("SEED"));
+        assert!(!missing_path.exists());
+    }
+
+    set_read_only(false);
+    assert_eq!(
+        resolve_seed(missing_path.to_str().unwrap(), None).unwrap(),
+        seed
+    );
+    assert!(missing_path.exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
 #[test]
 fn estimate_circuit_rows() {
     use zkevm::circuit::{self, TargetCircuit};
@@ -55,6 +187,786 @@ fn estimate_circuit_rows() {
     log::info!("super circuit: {:?}", rows);
 }
 
+#[test]
+fn test_canonical_trace_hash() {
+    use serde_json::Value;
+    use types::eth::BlockTrace;
+    use zkevm::utils::{canonical_trace_hash, get_block_trace_from_file};
+
+    // Re-encode a json value with reversed key order and uppercase hex digits.
+    fn reencode(value: &Value) -> String {
+        match value {
+            Value::Object(map) => format!(
+                "{{{}}}",
+                map.iter()
+                    .rev()
+                    .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), reencode(v)))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Value::Array(values) => format!(
+                "[{}]",
+                values.iter().map(reencode).collect::<Vec<_>>().join(",")
+            ),
+            Value::String(s) if s.starts_with("0x") => {
+                Value::String(format!("0x{}", s[2..].to_uppercase())).to_string()
+            }
+            v => v.to_string(),
+        }
+    }
+
+    init();
+    let trace_path = "./tests/traces/greeter.json";
+    let trace = get_block_trace_from_file(trace_path);
+
+    let raw: Value = serde_json::from_slice(&std::fs::read(trace_path).unwrap()).unwrap();
+    let variant: BlockTrace = serde_json::from_str(&reencode(&raw)).unwrap();
+    assert_eq!(canonical_trace_hash(&trace), canonical_trace_hash(&variant));
+
+    let mut tampered = trace.clone();
+    let value = tampered
+        .execution_results
+        .iter_mut()
+        .flat_map(|r| r.exec_steps.iter_mut())
+        .find_map(|step| step.storage.as_mut().and_then(|s| s.values_mut().next()))
+        .expect("greeter trace should contain a storage write");
+    *value += 1;
+    assert_ne!(
+        canonical_trace_hash(&trace),
+        canonical_trace_hash(&tampered)
+    );
+}
+
+#[test]
+fn test_access_list_trace() {
+    use eth_types::U256;
+    use types::eth::TransactionTrace;
+    use zkevm::circuit::{block_traces_to_witness_block, L1_MESSAGE_TX_TYPE};
+    use zkevm::utils::get_block_trace_from_file;
+    use zkevm_circuits::witness::Rw;
+
+    init();
+    let trace = get_block_trace_from_file("./tests/traces/bridge/depositETH.json");
+    let tx = trace.transactions[0].to_eth_tx(None, None, None);
+    assert_eq!(trace.transactions[0].type_, 1);
+    assert_eq!(tx.transaction_type, Some(1.into()));
+    assert_eq!(tx.access_list.map(|list| list.0.len()), Some(0));
+
+    let legacy_trace = get_block_trace_from_file("./tests/traces/native_transfer.json");
+    let legacy_tx = legacy_trace.transactions[0].to_eth_tx(None, None, None);
+    assert!(legacy_tx.transaction_type.is_none());
+    assert!(legacy_tx.access_list.is_none());
+
+    let l1_message = TransactionTrace {
+        type_: L1_MESSAGE_TX_TYPE,
+        ..legacy_trace.transactions[0].clone()
+    };
+    let l1_message_tx = l1_message.to_eth_tx(None, None, None);
+    assert!(l1_message_tx.transaction_type.is_none());
+    assert!(l1_message_tx.access_list.is_none());
+
+    // the warm slots of a non-empty access list reach the witness. The fixture is depositETH
+    // with the slot its first SLOAD reads listed, so it is witness generated but not proved.
+    let trace = get_block_trace_from_file("./tests/traces/bridge/depositETH_access_list.json");
+    let entry = trace.transactions[0].access_list[0].clone();
+    let access_list = trace.transactions[0]
+        .to_eth_tx(None, None, None)
+        .access_list
+        .unwrap();
+    assert_eq!(access_list.0.len(), 1);
+    assert_eq!(access_list.0[0].storage_keys, entry.storage_keys);
+    let witness_block = block_traces_to_witness_block(&[trace]).unwrap();
+    let warm_slots: Vec<_> = witness_block
+        .rws
+        .0
+        .values()
+        .flatten()
+        .filter_map(|rw| match rw {
+            Rw::TxAccessListAccountStorage {
+                tx_id: 1,
+                account_address,
+                storage_key,
+                ..
+            } => Some((*account_address, *storage_key)),
+            _ => None,
+        })
+        .collect();
+    assert!(warm_slots.contains(&(
+        entry.address,
+        U256::from_big_endian(entry.storage_keys[0].as_bytes())
+    )));
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_mock_prove_access_list_tx() {
+    use zkevm::circuit::SuperCircuit;
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let trace = get_block_trace_from_file("./tests/traces/bridge/depositETH.json");
+    assert_eq!(trace.transactions[0].type_, 1);
+    Prover::mock_prove_target_circuit::<SuperCircuit>(&trace).unwrap();
+}
+
+#[test]
+fn test_block_trace_to_json_compact() {
+    use zkevm::circuit::RowUsageReport;
+    use zkevm::utils::{
+        block_trace_to_json_compact, filter_block_trace_for_circuit, get_block_trace_from_file,
+    };
+
+    init();
+    let trace = get_block_trace_from_file("./tests/traces/sushi/chef_withdraw.json");
+    let pretty = serde_json::to_vec_pretty(&trace).unwrap();
+    let mut compact = Vec::new();
+    block_trace_to_json_compact(&trace, &mut compact).unwrap();
+    log::info!(
+        "pretty: {} bytes, compact: {} bytes",
+        pretty.len(),
+        compact.len()
+    );
+    assert!(compact.len() * 5 <= pretty.len() * 4);
+
+    let mut filtered = Vec::new();
+    block_trace_to_json_compact(&filter_block_trace_for_circuit(&trace), &mut filtered).unwrap();
+    assert!(filtered.len() <= compact.len());
+    let parsed: types::eth::BlockTrace = serde_json::from_slice(&filtered).unwrap();
+    assert_eq!(parsed.chain_id, trace.chain_id);
+    assert_eq!(parsed.header.number, trace.header.number);
+    assert_eq!(parsed.transactions.len(), trace.transactions.len());
+
+    // the filtered trace generates the same witness as the original one
+    let original_rows = RowUsageReport::from_block_traces(&[trace]).unwrap();
+    let filtered_rows = RowUsageReport::from_block_traces(&[parsed]).unwrap();
+    assert_eq!(filtered_rows.per_block, original_rows.per_block);
+}
+
+#[test]
+fn test_suggest_rechunk() {
+    use zkevm::circuit::{
+        check_batch_capacity_with_policy, BatchPolicy, CircuitCapacityExceeded, RowUsageReport,
+    };
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let block_traces: Vec<_> = (1..=10)
+        .map(|i| get_block_trace_from_file(format!("tests/traces/bridge/{:02}.json", i)))
+        .collect();
+    let measured = RowUsageReport::from_block_traces(&block_traces).unwrap();
+
+    // every block fits on its own, but the whole batch does not
+    let policy = BatchPolicy {
+        max_rows: measured
+            .per_block
+            .iter()
+            .flat_map(|usage| usage.iter().copied())
+            .max()
+            .unwrap()
+            + 1,
+        auto_truncate: false,
+        ..Default::default()
+    };
+    let err = check_batch_capacity_with_policy(&mut block_traces.clone(), &policy).unwrap_err();
+    let err = err.downcast_ref::<CircuitCapacityExceeded>().unwrap();
+    log::info!("{}", err);
+    assert!(err.suggested_split.len() > 1);
+
+    // the split covers all blocks, in order
+    let mut remaining = &block_traces[..];
+    for range in &err.suggested_split {
+        let len = remaining
+            .iter()
+            .take_while(|b| range.contains(&b.header.number.unwrap().as_u64()))
+            .count();
+        assert!(len > 0);
+        let (chunk, rest) = remaining.split_at(len);
+        assert_eq!(chunk[0].header.number.unwrap().as_u64(), *range.start());
+        assert_eq!(chunk[len - 1].header.number.unwrap().as_u64(), *range.end());
+
+        let mut chunk = chunk.to_vec();
+        check_batch_capacity_with_policy(&mut chunk, &policy).unwrap();
+        assert_eq!(chunk.len(), len);
+        remaining = rest;
+    }
+    assert!(remaining.is_empty());
+
+    // the parallel capacity check truncates where the sequential one does
+    let truncated = [false, true].map(|parallel| {
+        let policy = BatchPolicy {
+            auto_truncate: true,
+            parallel,
+            ..policy.clone()
+        };
+        let mut batch = block_traces.clone();
+        check_batch_capacity_with_policy(&mut batch, &policy).unwrap();
+        batch.len()
+    });
+    assert!(truncated[0] < block_traces.len());
+    assert_eq!(truncated[0], truncated[1]);
+
+    let split = Prover::suggest_rechunk(&block_traces, &measured).unwrap();
+    assert_eq!(
+        *split[0].start(),
+        block_traces[0].header.number.unwrap().as_u64()
+    );
+    assert!(Prover::suggest_rechunk(&block_traces[1..], &measured).is_err());
+}
+
+// The capacity check of the prove path over the fixture blocks, 55 transactions in total, is
+// at least 25% faster in parallel on 8 cores.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn bench_witness_generation() {
+    use std::time::Instant;
+    use zkevm::circuit::{check_batch_capacity_with_policy, BatchPolicy};
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let block_traces: Vec<_> = glob::glob("./tests/traces/**/*.json")
+        .unwrap()
+        .map(|path| get_block_trace_from_file(path.unwrap()))
+        .filter(|block_trace| !block_trace.transactions.is_empty())
+        .collect();
+    let num_txs: usize = block_traces.iter().map(|b| b.transactions.len()).sum();
+    assert!(num_txs >= 50);
+
+    let check = |parallel| {
+        let policy = BatchPolicy {
+            max_rows: usize::MAX,
+            max_blocks: block_traces.len(),
+            auto_truncate: false,
+            parallel,
+        };
+        let mut batch = block_traces.clone();
+        let t = Instant::now();
+        check_batch_capacity_with_policy(&mut batch, &policy).unwrap();
+        assert_eq!(batch.len(), block_traces.len());
+        t.elapsed()
+    };
+    let sequential_time = check(false);
+    let parallel_time = check(true);
+
+    let cores = std::thread::available_parallelism().map_or(1, usize::from);
+    log::info!(
+        "{} blocks, {} txs, {} cores, capacity check: sequential {:?}, parallel {:?}",
+        block_traces.len(),
+        num_txs,
+        cores,
+        sequential_time,
+        parallel_time
+    );
+    if cores >= 8 {
+        assert!(parallel_time.as_secs_f64() <= 0.75 * sequential_time.as_secs_f64());
+    }
+}
+
+#[test]
+fn test_describe_super_circuit() {
+    let description = SuperCircuit::describe();
+    assert_eq!(description.name, SuperCircuit::name());
+    assert!(description.sub_circuits.contains(&"evm"));
+    assert!(description.sub_circuits.contains(&"state"));
+    assert_eq!(
+        description
+            .public_inputs
+            .iter()
+            .map(|field| field.len)
+            .sum::<usize>(),
+        SuperCircuit::public_input_len()
+    );
+}
+
+// Set UPDATE_GOLDEN=true to regenerate the golden summary after an intended circuit change.
+#[test]
+fn test_constraint_summary_golden() {
+    init();
+    assert_constraint_summary_golden::<SuperCircuit>(
+        "./tests/golden/super_circuit_constraints.json",
+    );
+}
+
+#[test]
+fn test_required_params_degree() {
+    // the degree the MAX_* limits are sized for, not the env-overridable DEGREE
+    assert_eq!(SuperCircuit::required_params_degree(), 20);
+}
+
+#[test]
+fn test_gas_accounting() {
+    use zkevm::circuit::{check_gas_accounting, BatchGasUsage, GasAccountingMismatch};
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let mut block_traces: Vec<_> = (1..=10)
+        .map(|i| get_block_trace_from_file(format!("tests/traces/bridge/{:02}.json", i)))
+        .collect();
+    check_gas_accounting(&block_traces).unwrap();
+    let gas_usage = BatchGasUsage::from_block_traces(&block_traces);
+    assert_eq!(gas_usage.per_block[5].gas_used, 4139002);
+    assert_eq!(
+        gas_usage.total_gas_used(),
+        518315 + 4139002 + 3914690 + 380459 + 12975386 + 788023
+    );
+
+    block_traces[5].header.gas_used += 1u64;
+    let err = Prover::mock_prove_target_circuit_batch::<SuperCircuit>(&block_traces).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<GasAccountingMismatch>(),
+        Some(&GasAccountingMismatch {
+            block: 6,
+            header: 4139003,
+            computed: 4139002,
+        })
+    );
+}
+
+#[test]
+fn test_gas_accounting_of_fixtures() {
+    use zkevm::circuit::check_gas_accounting;
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    // every prove runs the check, so no fixture may fail it
+    for path in glob::glob("./tests/traces/**/*.json").unwrap() {
+        let path = path.unwrap();
+        let block_trace = get_block_trace_from_file(&path);
+        if let Err(e) = check_gas_accounting(std::slice::from_ref(&block_trace)) {
+            panic!("{:?}: {}", path, e);
+        }
+    }
+}
+
+#[test]
+fn test_calldata_too_large() {
+    use eth_types::H256;
+    use zkevm::circuit::{check_calldata_size, CalldataTooLarge};
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let mut block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    check_calldata_size(&[block_trace.clone()]).unwrap();
+
+    // learn the limit from a transaction far over it
+    block_trace.transactions[0].data = vec![1u8; 1 << 24].into();
+    let limit = check_calldata_size(&[block_trace.clone()])
+        .unwrap_err()
+        .limit;
+
+    // just over the limit: rejected before any witness generation
+    block_trace.transactions[0].data = vec![1u8; limit + 1].into();
+    let err = check_calldata_size(&[block_trace.clone()]).unwrap_err();
+    assert_eq!(err.tx_hash, block_trace.transactions[0].tx_hash);
+    assert_eq!(err.len, limit + 1);
+    assert_eq!(err.total, limit + 1);
+    let err = Prover::mock_prove_target_circuit_batch::<SuperCircuit>(&[block_trace.clone()])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CalldataTooLarge>().unwrap().len,
+        limit + 1
+    );
+
+    // just under the limit
+    block_trace.transactions[0].data = vec![1u8; limit].into();
+    check_calldata_size(&[block_trace.clone()]).unwrap();
+
+    // every transaction fits on its own, but the batch does not
+    let mut first = block_trace.clone();
+    first.transactions[0].data = vec![1u8; limit / 2 + 1].into();
+    let mut second = first.clone();
+    second.transactions[0].tx_hash = H256::repeat_byte(0x22);
+    check_calldata_size(&[first.clone()]).unwrap();
+    check_calldata_size(&[second.clone()]).unwrap();
+    let err = check_calldata_size(&[first.clone(), second.clone()]).unwrap_err();
+    assert_eq!(err.tx_hash, second.transactions[0].tx_hash);
+    assert_eq!(err.len, limit / 2 + 1);
+    assert_eq!(err.total, 2 * (limit / 2 + 1));
+    let err =
+        Prover::mock_prove_target_circuit_batch::<SuperCircuit>(&[first, second]).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CalldataTooLarge>().unwrap().tx_hash,
+        H256::repeat_byte(0x22)
+    );
+}
+
+/// A batch whose calldata is exactly at the limit gets past the calldata check and through the
+/// capacity check into the circuit. The padded transaction no longer matches its signature and
+/// its gas, so the proof itself is not expected to be satisfied.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_mock_prove_calldata_at_limit() {
+    use zkevm::circuit::{check_calldata_size, CalldataTooLarge, CircuitCapacityExceeded};
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let mut block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    block_trace.transactions[0].data = vec![1u8; 1 << 24].into();
+    let limit = check_calldata_size(&[block_trace.clone()])
+        .unwrap_err()
+        .limit;
+    block_trace.transactions[0].data = vec![1u8; limit].into();
+    if let Err(err) = Prover::mock_prove_target_circuit_batch::<SuperCircuit>(&[block_trace]) {
+        assert!(err.downcast_ref::<CalldataTooLarge>().is_none(), "{err}");
+        assert!(
+            err.downcast_ref::<CircuitCapacityExceeded>().is_none(),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_fee_cap_below_base_fee() {
+    use zkevm::circuit::{check_fee_caps, FeeCapBelowBaseFee, DYNAMIC_FEE_TX_TYPE};
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let mut block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    let base_fee_per_gas = 1_000_000_000u64.into();
+    block_trace.header.base_fee_per_gas = Some(base_fee_per_gas);
+    block_trace.transactions[0].type_ = DYNAMIC_FEE_TX_TYPE;
+    block_trace.transactions[0].max_fee_per_gas = Some(base_fee_per_gas);
+    check_fee_caps(&[block_trace.clone()]).unwrap();
+
+    block_trace.transactions[0].max_fee_per_gas = Some(base_fee_per_gas - 1);
+    let err = check_fee_caps(&[block_trace.clone()]).unwrap_err();
+    assert_eq!(err.tx_hash, block_trace.transactions[0].tx_hash);
+    assert_eq!(err.base_fee_per_gas, base_fee_per_gas);
+    let err = Prover::mock_prove_target_circuit_batch::<SuperCircuit>(&[block_trace.clone()])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<FeeCapBelowBaseFee>()
+            .unwrap()
+            .max_fee_per_gas,
+        base_fee_per_gas - 1
+    );
+
+    // pre-London blocks have no base fee to check against
+    block_trace.header.base_fee_per_gas = None;
+    check_fee_caps(&[block_trace]).unwrap();
+}
+
+#[test]
+fn test_l1_message_accounting() {
+    use zkevm::circuit::{
+        L1MessageAccounting, L1MessageAccountingError, L1MessageQueueSnapshot, L1_MESSAGE_TX_TYPE,
+    };
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    let snapshot = L1MessageQueueSnapshot {
+        next_queue_index: 5,
+    };
+
+    // no L1 message: nothing popped from the queue
+    let accounting = L1MessageAccounting::from_traces(&[block_trace.clone()], snapshot).unwrap();
+    assert!(accounting.included.is_empty());
+    assert!(accounting.skipped_bitmap.is_empty());
+    assert_eq!(accounting.next_queue_index, 5);
+
+    // messages 6 and 7 included, so the first pending message 5 is skipped
+    let with_l1_messages = |queue_indices: &[u64]| {
+        let mut block_trace = block_trace.clone();
+        for &queue_index in queue_indices {
+            let mut tx = block_trace.transactions[0].clone();
+            tx.type_ = L1_MESSAGE_TX_TYPE;
+            tx.nonce = queue_index;
+            block_trace.transactions.insert(0, tx);
+        }
+        block_trace
+    };
+    let accounting =
+        L1MessageAccounting::from_traces(&[with_l1_messages(&[7, 6])], snapshot).unwrap();
+    assert_eq!(accounting.included, vec![6, 7]);
+    assert_eq!(accounting.next_queue_index, 8);
+    assert_eq!(accounting.num_popped(), 3);
+    assert!(accounting.is_skipped(5));
+    assert!(!accounting.is_skipped(6));
+    let mut bitmap = [0u8; 32];
+    bitmap[31] = 1;
+    assert_eq!(accounting.skipped_bitmap_bytes(), bitmap);
+
+    // message 4 was consumed by a previous chunk
+    let err = L1MessageAccounting::from_traces(&[with_l1_messages(&[6, 4])], snapshot).unwrap_err();
+    assert_eq!(err.queue_index, 4);
+    let err: anyhow::Error = err.into();
+    assert!(err.downcast_ref::<L1MessageAccountingError>().is_some());
+}
+
+#[test]
+fn test_vk_pem() {
+    use zkevm::io::{pem_to_vk, vk_to_pem};
+
+    let vk: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let pem = vk_to_pem(&vk);
+    let lines: Vec<_> = pem.lines().collect();
+    assert_eq!(lines[0], "-----BEGIN SCROLL CIRCUIT VK-----");
+    assert_eq!(lines[lines.len() - 1], "-----END SCROLL CIRCUIT VK-----");
+    assert!(lines.iter().all(|line| line.len() <= 64));
+    assert_eq!(pem_to_vk(&pem).unwrap(), vk);
+    assert_eq!(pem_to_vk(&pem.replace('\n', "\r\n")).unwrap(), vk);
+    assert!(pem_to_vk(&pem[1..]).is_err());
+    assert_eq!(pem_to_vk(&vk_to_pem(&[])).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn test_block_trace_lite() {
+    use zkevm::utils::{
+        get_block_trace_from_file, get_block_trace_lite_from_file, trace_cost_report,
+        trace_cost_report_lite,
+    };
+
+    init();
+    for path in glob::glob("./tests/traces/**/*.json").unwrap() {
+        let path = path.unwrap();
+        let lite = get_block_trace_lite_from_file(&path);
+        let full = get_block_trace_from_file(&path);
+        assert_eq!(
+            trace_cost_report_lite(std::slice::from_ref(&lite)),
+            trace_cost_report(std::slice::from_ref(&full)),
+            "{:?}",
+            path
+        );
+        assert_eq!(lite.header.hash, full.header.hash);
+        assert_eq!(lite.upgrade(&path).unwrap().header.hash, full.header.hash);
+    }
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn bench_block_trace_lite_parsing() {
+    use std::time::Instant;
+    use zkevm::utils::{get_block_trace_from_file, get_block_trace_lite_from_file};
+
+    init();
+    let path = "./tests/traces/erc20/multiple.json";
+    let t = Instant::now();
+    get_block_trace_from_file(path);
+    let full_time = t.elapsed();
+    let t = Instant::now();
+    get_block_trace_lite_from_file(path);
+    let lite_time = t.elapsed();
+    log::info!("parse {}: full {:?}, lite {:?}", path, full_time, lite_time);
+    assert!(lite_time * 2 < full_time);
+}
+
+#[test]
+fn test_sort_traces_by_block_number() {
+    use zkevm::utils::{
+        get_block_trace_from_file, sort_named_traces_by_block_number, sort_traces_by_block_number,
+    };
+
+    let block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    let with_number = |number: Option<u64>| {
+        let mut block_trace = block_trace.clone();
+        block_trace.header.number = number.map(Into::into);
+        block_trace
+    };
+    let numbers = [Some(5), None, Some(2), Some(4), Some(1), Some(3)];
+    let mut traces: Vec<_> = numbers.into_iter().map(with_number).collect();
+    sort_traces_by_block_number(&mut traces);
+    let sorted: Vec<_> = traces
+        .iter()
+        .map(|block_trace| block_trace.header.number.map(|number| number.as_u64()))
+        .collect();
+    assert_eq!(sorted, [Some(1), Some(2), Some(3), Some(4), Some(5), None]);
+
+    let mut named: Vec<_> = numbers
+        .into_iter()
+        .map(|number| (format!("{:?}", number), with_number(number)))
+        .collect();
+    sort_named_traces_by_block_number(&mut named);
+    let names: Vec<_> = named.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["Some(1)", "Some(2)", "Some(3)", "Some(4)", "Some(5)", "None"]
+    );
+}
+
+#[test]
+fn test_load_batch_traces_bounded() {
+    use zkevm::utils::{load_batch_traces_bounded, BatchError};
+
+    init();
+    let batch_dir = "./tests/traces/bridge/swap";
+    match load_batch_traces_bounded(batch_dir, 1) {
+        Err(BatchError::ExceededMemoryBudget { loaded, skipped }) => {
+            assert_eq!(loaded, 0);
+            assert_eq!(skipped, 3);
+        }
+        other => panic!("unexpected {:?}", other.map(|(names, _)| names)),
+    }
+
+    let (names, traces) = load_batch_traces_bounded(batch_dir, usize::MAX).unwrap();
+    assert_eq!(names.len(), 3);
+    assert!(matches!(
+        load_batch_traces_bounded("./tests/traces/[", usize::MAX),
+        Err(BatchError::Io(_))
+    ));
+    assert!(traces
+        .windows(2)
+        .all(|w| w[0].header.number <= w[1].header.number));
+}
+
+/// Join lines into a ndjson buffer, inlining the lines that name a trace file.
+fn ndjson_for_test(lines: &[&str]) -> Vec<u8> {
+    lines
+        .iter()
+        .map(|line| {
+            if line.ends_with(".json") {
+                let trace: serde_json::Value =
+                    serde_json::from_slice(&std::fs::read(line).unwrap()).unwrap();
+                serde_json::to_string(&trace).unwrap()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .into_bytes()
+}
+
+#[test]
+fn test_read_block_traces_ndjson() {
+    use zkevm::utils::{
+        read_block_traces_ndjson, read_block_traces_ndjson_with_policy, NdjsonErrorPolicy,
+    };
+
+    init();
+    let buf = ndjson_for_test(&[
+        "./tests/traces/native_transfer.json",
+        "{\"header\": ",
+        "./tests/traces/greeter.json",
+    ]);
+
+    let results: Vec<_> = read_block_traces_ndjson(buf.as_slice()).collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    let err = results[1].as_ref().unwrap_err();
+    assert!(format!("{:#}", err).starts_with("line 2"), "{:#}", err);
+
+    let results: Vec<_> =
+        read_block_traces_ndjson_with_policy(buf.as_slice(), NdjsonErrorPolicy::Continue).collect();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(format!("{:#}", results[1].as_ref().unwrap_err()).starts_with("line 2"));
+    assert!(results[2].is_ok());
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_mock_prove_ndjson() {
+    use zkevm::circuit;
+    use zkevm::utils::read_block_traces_ndjson;
+
+    init();
+    let buf = ndjson_for_test(&[
+        "./tests/traces/native_transfer.json",
+        "",
+        "./tests/traces/greeter.json",
+    ]);
+    let block_traces = read_block_traces_ndjson(buf.as_slice())
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(block_traces.len(), 2);
+    Prover::mock_prove_target_circuit_batch::<circuit::SuperCircuit>(&block_traces).unwrap();
+}
+
+// Changing a cell of the vectors changes the digest of its column only.
+#[test]
+fn test_test_vector_digests() {
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use std::collections::HashMap;
+    use zkevm::circuit::{
+        verify_against_test_vector_digests, verify_against_test_vectors, TestVectors,
+    };
+
+    let row = |cells: &[(&str, u64)]| {
+        cells
+            .iter()
+            .map(|(column, value)| (column.to_string(), Fr::from(*value)))
+            .collect::<HashMap<_, _>>()
+    };
+    let reference = TestVectors {
+        rows: vec![
+            row(&[("advice_0", 1), ("advice_1", 2), ("instance_0", 3)]),
+            row(&[("advice_0", 4)]),
+        ],
+    };
+    let digests = reference.digests();
+    assert_eq!(
+        digests.columns.keys().collect::<Vec<_>>(),
+        ["advice_0", "advice_1", "instance_0"]
+    );
+
+    let mut computed = reference.clone();
+    *computed.rows[1].get_mut("advice_0").unwrap() += Fr::one();
+    let mismatches = verify_against_test_vectors(&computed, &reference);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(
+        (mismatches[0].row, mismatches[0].column.as_str()),
+        (1, "advice_0")
+    );
+    assert_eq!(
+        verify_against_test_vector_digests(&computed.digests(), &digests),
+        ["advice_0"]
+    );
+
+    // the same value in another row is another column digest
+    let mut computed = reference.clone();
+    let value = computed.rows[1].remove("advice_0").unwrap();
+    computed.rows[0].insert("advice_0".to_string(), value);
+    assert_eq!(
+        verify_against_test_vector_digests(&computed.digests(), &digests),
+        ["advice_0"]
+    );
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_greeter_test_vectors() {
+    use zkevm::circuit::{
+        generate_test_vectors, verify_against_test_vector_digests, TestVectorDigests,
+    };
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    let computed = generate_test_vectors::<SuperCircuit>(&[block_trace]).unwrap();
+    assert!(!computed.rows.is_empty());
+    assert!(computed
+        .rows
+        .iter()
+        .flat_map(|row| row.keys())
+        .all(|column| column.starts_with("advice_") || column.starts_with("instance_")));
+    let computed = computed.digests();
+
+    let reference_path = "./tests/golden/greeter_test_vectors.json";
+    if read_env_var("UPDATE_GOLDEN", false) {
+        std::fs::write(
+            reference_path,
+            serde_json::to_vec_pretty(&computed).unwrap(),
+        )
+        .unwrap();
+        log::info!("wrote {}, review and commit it", reference_path);
+        return;
+    }
+
+    let reference = std::fs::read(reference_path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read the reference {}: {}, set UPDATE_GOLDEN=true to write it",
+            reference_path, e
+        )
+    });
+    let reference: TestVectorDigests = serde_json::from_slice(&reference).unwrap();
+    let changed = verify_against_test_vector_digests(&computed, &reference);
+    assert!(
+        changed.is_empty(),
+        "greeter witness changed in columns {:?}, set UPDATE_GOLDEN=true if intended",
+        changed
+    );
+}
+
 #[cfg(feature = "prove_verify")]
 #[test]
 fn test_mock_prove() {
@@ -73,6 +985,101 @@ fn test_prove_verify() {
     test_target_circuit_prove_verify::<SuperCircuit>();
 }
 
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_prove_redundant() {
+    use zkevm::utils::get_block_trace_from_file;
+
+    init();
+    let block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    let mut prover = Prover::from_fpath(PARAMS_DIR, Some(SEED_PATH));
+    let result = prover
+        .prove_redundant::<SuperCircuit>(&[block_trace], [[0u8; 16], [1u8; 16]])
+        .unwrap();
+    assert_ne!(result.run_ids[0], result.run_ids[1]);
+    assert_ne!(result.proofs[0].snark.proof, result.proofs[1].snark.proof);
+    assert_eq!(
+        result.proofs[0].snark.instances,
+        result.proofs[1].snark.instances
+    );
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_prove_incremental() {
+    use eth_types::H256;
+    use zkevm::circuit::block_traces_to_witness_block;
+    use zkevm::prover::StateRootMismatch;
+    use zkevm::utils::get_block_trace_from_file;
+    use zkevm::verifier::Verifier;
+
+    init();
+    // consecutive blocks which each change the state root
+    let block_traces = ["05", "06", "07"]
+        .map(|name| get_block_trace_from_file(format!("./tests/traces/bridge/{}.json", name)));
+    for block_trace in &block_traces {
+        assert_ne!(
+            block_trace.storage_trace.root_before,
+            block_trace.storage_trace.root_after
+        );
+    }
+    let mut prover = Prover::from_fpath(PARAMS_DIR, Some(SEED_PATH));
+    let mut verifier = Verifier::from_fpath(PARAMS_DIR, None);
+    for block_trace in block_traces.clone() {
+        let proof = prover
+            .create_proof_incremental::<SuperCircuit>(block_trace)
+            .unwrap();
+        verifier
+            .verify_target_circuit_proof::<SuperCircuit>(&proof)
+            .unwrap();
+    }
+
+    // the batch of the same blocks commits to the state root the chain ended at
+    let mut rng = prover.rng.clone();
+    let batch_proof = prover
+        .create_target_circuit_proof_batch::<SuperCircuit>(&block_traces, &mut rng)
+        .unwrap();
+    verifier
+        .verify_target_circuit_proof::<SuperCircuit>(&batch_proof)
+        .unwrap();
+    let witness_block = block_traces_to_witness_block(&block_traces).unwrap();
+    let (_, instances) = SuperCircuit::from_witness_block(&witness_block).unwrap();
+    assert_eq!(batch_proof.snark.instances, instances);
+    assert_eq!(
+        Some(H256::from_uint(&witness_block.mpt_updates.new_root())),
+        prover.running_state_root()
+    );
+
+    // a block which does not continue from the running state root is refused before proving
+    let block_trace = block_traces[0].clone();
+    let err = prover
+        .create_proof_incremental::<SuperCircuit>(block_trace)
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<StateRootMismatch>().unwrap().expected,
+        block_traces[2].storage_trace.root_after
+    );
+}
+
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_prove_verify_random_seed() {
+    use zkevm::utils::get_block_trace_from_file;
+    use zkevm::verifier::Verifier;
+
+    init();
+    let block_trace = get_block_trace_from_file("./tests/traces/greeter.json");
+    let mut prover = Prover::from_fpath(PARAMS_DIR, None);
+    let mut rng = prover.rng.clone();
+    let proof = prover
+        .create_target_circuit_proof::<SuperCircuit>(&block_trace, &mut rng)
+        .unwrap();
+    let mut verifier = Verifier::from_fpath(PARAMS_DIR, None);
+    verifier
+        .verify_target_circuit_proof::<SuperCircuit>(&proof)
+        .unwrap();
+}
+
 #[cfg(feature = "prove_verify")]
 #[test]
 fn test_deterministic() {
@@ -187,7 +1194,7 @@ fn test_target_circuit_prove_verify<C: TargetCircuit>() {
 
     log::info!("start generating {} proof", C::name());
     let now = Instant::now();
-    let mut prover = Prover::from_fpath(PARAMS_DIR, SEED_PATH);
+    let mut prover = Prover::from_fpath(PARAMS_DIR, Some(SEED_PATH));
     let proof = prover
         .create_target_circuit_proof_batch::<C>(&block_traces, &mut rng)
         .unwrap();
@@ -1,14 +1,18 @@
 //! A module for Mock Plonk circuit.
 //!
 use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::poly::commitment::Params;
 use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner, Value},
     plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance},
     poly::Rotation,
 };
-use rand::RngCore;
+use rand::{RngCore, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use snark_verifier::loader::halo2::halo2_ecc::halo2_base::utils::fs::gen_srs;
 use snark_verifier_sdk::CircuitExt;
 use zkevm::circuit::TargetCircuit;
+use zkevm::prover::{Prover, TargetCircuitProof};
 use zkevm_circuits::witness;
 
 pub struct MockPlonkCircuit {
@@ -44,6 +48,42 @@ impl TargetCircuit for MockPlonkCircuit {
     {
         unimplemented!()
     }
+
+    fn public_input_len() -> usize {
+        1
+    }
+}
+
+/// Another version of [`MockPlonkCircuit`]: same name and constraint system, but a different
+/// vk since its permutation differs.
+pub struct MockPlonkCircuitV2;
+
+impl TargetCircuit for MockPlonkCircuitV2 {
+    type Inner = StandardPlonkV2;
+
+    fn name() -> String {
+        MockPlonkCircuit::name()
+    }
+
+    fn dummy_inner_circuit() -> Self::Inner
+    where
+        Self: Sized,
+    {
+        StandardPlonkV2(StandardPlonk(Fr::zero()))
+    }
+
+    fn from_witness_block(
+        _witness_block: &witness::Block<Fr>,
+    ) -> anyhow::Result<(Self::Inner, Vec<Vec<Fr>>)>
+    where
+        Self: Sized,
+    {
+        unimplemented!()
+    }
+
+    fn public_input_len() -> usize {
+        1
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -132,10 +172,17 @@ impl Circuit<Fr> for StandardPlonk {
         StandardPlonkConfig::configure(meta)
     }
 
-    fn synthesize(
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        self.synthesize_version(config, layouter, 1)
+    }
+}
+
+impl StandardPlonk {
+    fn synthesize_version(
         &self,
-        config: Self::Config,
+        config: StandardPlonkConfig,
         mut layouter: impl Layouter<Fr>,
+        version: usize,
     ) -> Result<(), Error> {
         layouter.assign_region(
             || "",
@@ -155,9 +202,85 @@ impl Circuit<Fr> for StandardPlonk {
                 let a = region.assign_advice(|| "", config.a, 2, || Value::known(Fr::one()))?;
                 a.copy_advice(|| "", &mut region, config.b, 3)?;
                 a.copy_advice(|| "", &mut region, config.c, 4)?;
+                if version > 1 {
+                    a.copy_advice(|| "", &mut region, config.b, 5)?;
+                }
 
                 Ok(())
             },
         )
     }
 }
+
+/// [`StandardPlonk`] with one more copy constraint.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StandardPlonkV2(pub StandardPlonk);
+
+impl CircuitExt<Fr> for StandardPlonkV2 {
+    fn num_instance(&self) -> Vec<usize> {
+        self.0.num_instance()
+    }
+
+    fn instances(&self) -> Vec<Vec<Fr>> {
+        self.0.instances()
+    }
+}
+
+impl Circuit<Fr> for StandardPlonkV2 {
+    type Config = StandardPlonkConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        StandardPlonk::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        self.0.synthesize_version(config, layouter, 2)
+    }
+}
+
+/// Prove `circuit` as a [`MockPlonkCircuit`].
+pub fn prove_mock_plonk(
+    prover: &mut Prover,
+    circuit: StandardPlonk,
+    rng: &mut XorShiftRng,
+) -> TargetCircuitProof {
+    prover
+        .create_target_circuit_proof_from_circuit::<MockPlonkCircuit>(
+            circuit,
+            circuit.instances(),
+            rng,
+            0,
+            0,
+        )
+        .unwrap()
+}
+
+/// A prover with params of degree `k`, a random [`StandardPlonk`] and a proof of it.
+pub fn mock_plonk_proof(k: u32) -> (Prover, StandardPlonk, TargetCircuitProof) {
+    mock_plonk_proof_with_agg(k, k)
+}
+
+/// [`mock_plonk_proof`] with aggregation params of degree `k_agg`, the target circuit params
+/// being downsized from them.
+pub fn mock_plonk_proof_with_agg(
+    k: u32,
+    k_agg: u32,
+) -> (Prover, StandardPlonk, TargetCircuitProof) {
+    let seed = [0u8; 16];
+    let mut rng = XorShiftRng::from_seed(seed);
+    let params_outer = gen_srs(k_agg);
+    let params_inner = {
+        let mut params = params_outer.clone();
+        params.downsize(k);
+        params
+    };
+    let circuit = StandardPlonk::rand(&mut rng);
+    let mut prover = Prover::from_params_and_seed(params_inner, params_outer, seed);
+    let proof = prove_mock_plonk(&mut prover, circuit, &mut rng);
+    (prover, circuit, proof)
+}
@@ -1,6 +1,7 @@
 use halo2_proofs::poly::commitment::Params;
-use mock_plonk::MockPlonkCircuit;
-use mock_plonk::StandardPlonk;
+use mock_plonk::{
+    mock_plonk_proof, mock_plonk_proof_with_agg, prove_mock_plonk, MockPlonkCircuit, StandardPlonk,
+};
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
 use snark_verifier::loader::halo2::halo2_ecc::halo2_base::utils::fs::gen_srs;
@@ -8,7 +9,8 @@ use snark_verifier_sdk::evm::{evm_verify, gen_evm_proof_shplonk, gen_evm_verifie
 use snark_verifier_sdk::halo2::aggregation::AggregationCircuit;
 use snark_verifier_sdk::CircuitExt;
 use snark_verifier_sdk::{gen_pk, halo2::gen_snark_shplonk};
-use test_util::init;
+use test_util::{assert_constraint_summary_golden, init};
+use zkevm::io::serialize_vk;
 use zkevm::prover::Prover;
 use zkevm::verifier::{EvmVerifier, Verifier};
 
@@ -93,45 +95,25 @@ fn test_partial_aggregation_api() {
     // ====================================================
     //
     // 1. instantiation the parameters and the prover
-    //
-    let k = 8;
-    let k_agg = 23;
-    let seed = [0u8; 16];
-    let mut rng = XorShiftRng::from_seed(seed);
-
-    // notice that k < k_agg which is not necessary the case in practice
-    let params_outer = gen_srs(k_agg);
-    let params_inner = {
-        let mut params = params_outer.clone();
-        params.downsize(k);
-        params
-    };
-    log::info!("loaded parameters for degrees {} and {}", k, k_agg);
-    let circuit = StandardPlonk::rand(&mut rng);
-    let mut prover = Prover::from_params_and_seed(params_inner.clone(), params_outer.clone(), seed);
-    //
     // 2. convert block traces into inner circuit proofs, a.k.a. SNARKs
     //
     // Note:
     // we do not have traces for testing so here we simply assume that we have already
     // obtained 3 inner circuits proofs for some dummy circuit
-    let target_circuit_proof = (0..num_snarks)
-        .map(|_| {
-            prover
-                .create_target_circuit_proof_from_circuit::<MockPlonkCircuit>(
-                    circuit,
-                    circuit.instances(),
-                    &mut rng,
-                    0,
-                    0,
-                )
-                .unwrap()
-        })
-        .collect::<Vec<_>>();
+    let k = 8;
+    let k_agg = 23;
+    // notice that k < k_agg which is not necessary the case in practice
+    let (mut prover, circuit, proof) = mock_plonk_proof_with_agg(k, k_agg);
+    let mut rng = prover.rng.clone();
+    let params_outer = prover.agg_params.clone();
+    log::info!("loaded parameters for degrees {} and {}", k, k_agg);
+    let mut target_circuit_proof = vec![proof];
+    target_circuit_proof
+        .extend((1..num_snarks).map(|_| prove_mock_plonk(&mut prover, circuit, &mut rng)));
     log::info!("finished inner circuit snark generation");
 
     // sanity check: the inner proof is correct
-    let mut verifier = Verifier::new(params_inner, params_outer.clone(), None);
+    let mut verifier = Verifier::new(prover.params.clone(), params_outer.clone(), None);
     for i in 0..num_snarks {
         verifier
             .verify_target_circuit_proof::<MockPlonkCircuit>(&target_circuit_proof[i])
@@ -166,3 +148,884 @@ fn test_partial_aggregation_api() {
     EvmVerifier::new(deployment_code).verify(instances, proof);
     log::info!("end to end test completed");
 }
+
+// Wrap a native transcript proof into an evm transcript proof.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_wrap_for_evm() {
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use zkevm::io::{load_instances, vk_fingerprint};
+
+    std::env::set_var("VERIFY_CONFIG", "./configs/example_evm_accumulator.config");
+
+    init();
+
+    let (mut prover, circuit, inner_proof) = mock_plonk_proof_with_agg(8, 21);
+    let mut rng = prover.rng.clone();
+    let outer_proof = prover.wrap_for_evm(&inner_proof, &mut rng).unwrap();
+    log::info!("finished wrapping");
+
+    // the outer instances are the accumulator limbs followed by the inner public inputs
+    let outer_instances = load_instances(&outer_proof.instance);
+    let outer_instances = &outer_instances[0][0];
+    let inner_instances: Vec<Fr> = circuit.instances().concat();
+    assert!(outer_instances.len() > inner_instances.len());
+    assert_eq!(
+        outer_instances[outer_instances.len() - inner_instances.len()..],
+        inner_instances[..]
+    );
+    assert_eq!(
+        outer_proof.inner_vk_fingerprints,
+        vec![vk_fingerprint(&inner_proof.vk)]
+    );
+
+    let agg_vk = serialize_vk(prover.agg_pk.as_ref().unwrap().get_vk());
+    assert_eq!(agg_vk, outer_proof.vk);
+    let verifier = Verifier::new(
+        prover.params.clone(),
+        prover.agg_params.clone(),
+        Some(agg_vk),
+    );
+    assert!(verifier.verify_agg_circuit_proof(outer_proof).unwrap());
+}
+
+// The evm verifier accepts a wrapped proof in revm, and rejects it once tampered with.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_verify_with_revm() {
+    use halo2_proofs::plonk::VerifyingKey;
+    use zkevm::io::load_instances;
+
+    std::env::set_var("VERIFY_CONFIG", "./configs/example_evm_accumulator.config");
+
+    init();
+
+    let (mut prover, _, inner_proof) = mock_plonk_proof_with_agg(8, 21);
+    let mut rng = prover.rng.clone();
+    let mut outer_proof = prover.wrap_for_evm(&inner_proof, &mut rng).unwrap();
+
+    let agg_vk: &VerifyingKey<_> = prover.agg_pk.as_ref().unwrap().get_vk();
+    let num_instance = load_instances(&outer_proof.instance)[0]
+        .iter()
+        .map(|column| column.len())
+        .collect();
+    let deployment_code = gen_evm_verifier_shplonk::<AggregationCircuit>(
+        &prover.agg_params,
+        agg_vk,
+        num_instance,
+        None,
+    );
+
+    let verifier = Verifier::new(prover.params.clone(), prover.agg_params.clone(), None);
+    assert!(verifier
+        .verify_with_revm(&outer_proof, &deployment_code)
+        .unwrap());
+
+    let len = outer_proof.proof.len();
+    outer_proof.proof[len / 2] ^= 1;
+    assert!(!verifier
+        .verify_with_revm(&outer_proof, &deployment_code)
+        .unwrap());
+}
+
+// The aggregation vk is read from disk only once, even across many verifications.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_verifier_agg_vk_cache() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Verifier>();
+
+    std::env::set_var("VERIFY_CONFIG", "./configs/example_evm_accumulator.config");
+
+    init();
+
+    let (mut prover, _, inner_proof) = mock_plonk_proof_with_agg(8, 21);
+    let mut rng = prover.rng.clone();
+    let outer_proof = prover.wrap_for_evm(&inner_proof, &mut rng).unwrap();
+
+    let vk_path = std::env::temp_dir().join("test_verifier_agg_vk_cache.vkey");
+    std::fs::write(&vk_path, &outer_proof.vk).unwrap();
+    let verifier = Verifier::from_params_and_agg_vk_path(
+        prover.params,
+        prover.agg_params,
+        vk_path.to_str().unwrap(),
+    );
+
+    assert!(verifier
+        .verify_agg_circuit_proof(outer_proof.clone())
+        .unwrap());
+    // the vk is cached after the first call, so later calls don't touch the file
+    std::fs::remove_file(&vk_path).unwrap();
+    for _ in 1..100 {
+        assert!(verifier
+            .verify_agg_circuit_proof(outer_proof.clone())
+            .unwrap());
+    }
+}
+
+// Vk files dropped into a watched directory are picked up by a running verifier,
+// malformed ones are quarantined.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_watch_vk_dir() {
+    use mock_plonk::{MockPlonkCircuitV2, StandardPlonkV2};
+    use std::time::{Duration, Instant};
+    use zkevm::circuit::TargetCircuit;
+    use zkevm::io::vk_fingerprint;
+    use zkevm::verifier::VK_QUARANTINE_DIR;
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    init();
+
+    let seed = [0u8; 16];
+    let (prover, circuit, proof) = mock_plonk_proof(8);
+    let mut rng = prover.rng;
+    let params = prover.params;
+
+    // a proof of another version of the circuit, with another vk
+    let mut prover_v2 = Prover::from_params_and_seed(params.clone(), params.clone(), seed);
+    let proof_v2 = prover_v2
+        .create_target_circuit_proof_from_circuit::<MockPlonkCircuitV2>(
+            StandardPlonkV2(circuit),
+            circuit.instances(),
+            &mut rng,
+            0,
+            0,
+        )
+        .unwrap();
+    assert_eq!(proof_v2.name, proof.name);
+    assert_ne!(proof_v2.vk, proof.vk);
+
+    let vk_dir = std::env::temp_dir().join("test_watch_vk_dir");
+    let _ = std::fs::remove_dir_all(&vk_dir);
+    std::fs::create_dir_all(&vk_dir).unwrap();
+    let mut verifier = Verifier::new(params.clone(), params, None);
+    let _watcher = verifier.watch_vk_dir::<MockPlonkCircuit>(&vk_dir, Duration::from_millis(20));
+    verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&proof)
+        .unwrap();
+    assert!(verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&proof_v2)
+        .is_err());
+
+    // a new vk version is registered without reconstructing the verifier
+    let fingerprint = vk_fingerprint(&proof_v2.vk);
+    let vk_path = vk_dir.join(format!("{}.{}.vkey", MockPlonkCircuit::name(), fingerprint));
+    std::fs::write(&vk_path, &proof_v2.vk).unwrap();
+    wait_until(|| {
+        verifier.registered_vk_fingerprints::<MockPlonkCircuit>() == [fingerprint.clone()]
+    });
+    verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&proof_v2)
+        .unwrap();
+    verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&proof)
+        .unwrap();
+
+    // malformed vk: the content doesn't match the fingerprint of the file name
+    let bad_name = format!("{}.0011223344556677.vkey", MockPlonkCircuit::name());
+    std::fs::write(vk_dir.join(&bad_name), b"not a vk").unwrap();
+    let quarantined = vk_dir.join(VK_QUARANTINE_DIR).join(&bad_name);
+    wait_until(|| quarantined.exists());
+    assert!(!vk_dir.join(&bad_name).exists());
+    assert_eq!(
+        verifier.registered_vk_fingerprints::<MockPlonkCircuit>(),
+        [fingerprint]
+    );
+
+    // a registered vk file rewritten with other content is quarantined and retired
+    std::fs::write(&vk_path, &proof.vk).unwrap();
+    wait_until(|| {
+        verifier
+            .registered_vk_fingerprints::<MockPlonkCircuit>()
+            .is_empty()
+    });
+    assert!(!vk_path.exists());
+    assert!(verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&proof_v2)
+        .is_err());
+
+    // removed vk files are retired
+    std::fs::write(&vk_path, &proof_v2.vk).unwrap();
+    wait_until(|| {
+        verifier.registered_vk_fingerprints::<MockPlonkCircuit>() == [fingerprint.clone()]
+    });
+    std::fs::remove_file(&vk_path).unwrap();
+    wait_until(|| {
+        verifier
+            .registered_vk_fingerprints::<MockPlonkCircuit>()
+            .is_empty()
+    });
+}
+
+// Cheap integrity checks catch a bad sidecar, only sampled full verification catches a bad proof.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_archive_integrity_check() {
+    use std::path::PathBuf;
+    use zkevm::prover::TargetCircuitProof;
+    use zkevm::verifier::{write_sidecar, IntegrityError, IntegrityLayer, ReplayOptions};
+
+    init();
+
+    let (prover, _, proof) = mock_plonk_proof(8);
+    let params = prover.params.clone();
+
+    let archive = std::env::temp_dir().join("test_archive_integrity_check");
+    std::fs::create_dir_all(&archive).unwrap();
+    let paths: Vec<PathBuf> = (0..16)
+        .map(|i| {
+            let path = archive.join(format!("{i}.json"));
+            proof.dump_to_file(path.to_str().unwrap()).unwrap();
+            write_sidecar(&path).unwrap();
+            path
+        })
+        .collect();
+    // a foreign vk is refused even if it is the first proof the verifier sees
+    let foreign_path = archive.join("foreign.json");
+    let mut foreign = prover
+        .create_proof_with_instance_override::<MockPlonkCircuit>(&proof, &[])
+        .unwrap();
+    foreign.vk[0] ^= 1;
+    foreign
+        .dump_to_file(foreign_path.to_str().unwrap())
+        .unwrap();
+    write_sidecar(&foreign_path).unwrap();
+    let mut verifier = Verifier::new(params.clone(), params, None);
+    let err = verifier
+        .integrity_check::<MockPlonkCircuit>(&foreign_path)
+        .unwrap_err();
+    let err = err.downcast_ref::<IntegrityError>().unwrap();
+    assert_eq!(err.layer, IntegrityLayer::VkFingerprint);
+    std::fs::remove_file(&foreign_path).unwrap();
+    std::fs::remove_file(zkevm::verifier::sidecar_path(&foreign_path)).unwrap();
+
+    for path in &paths {
+        verifier.integrity_check::<MockPlonkCircuit>(path).unwrap();
+    }
+
+    // corrupted sidecar
+    std::fs::write(zkevm::verifier::sidecar_path(&paths[0]), "00").unwrap();
+    let err = verifier
+        .integrity_check::<MockPlonkCircuit>(&paths[0])
+        .unwrap_err();
+    let err = err.downcast_ref::<IntegrityError>().unwrap();
+    assert_eq!(err.layer, IntegrityLayer::ContentHash);
+    assert_eq!(err.file, paths[0]);
+    write_sidecar(&paths[0]).unwrap();
+
+    // corrupted proof body with a matching sidecar passes the cheap checks
+    let mut bad_proof = TargetCircuitProof::restore_from_file(paths[1].to_str().unwrap())
+        .unwrap()
+        .unwrap();
+    let len = bad_proof.snark.proof.len();
+    bad_proof.snark.proof[len / 2] ^= 1;
+    bad_proof.dump_to_file(paths[1].to_str().unwrap()).unwrap();
+    write_sidecar(&paths[1]).unwrap();
+    verifier
+        .integrity_check::<MockPlonkCircuit>(&paths[1])
+        .unwrap();
+
+    let (sampled, not_sampled): (Vec<_>, Vec<_>) = (0..64)
+        .map(|seed| ReplayOptions::sample_full_verification(0.25, seed))
+        .partition(|options| options.is_sampled(&paths[1]));
+
+    let report = verifier.replay_archive::<MockPlonkCircuit>(&paths, &not_sampled[0]);
+    assert_eq!(report.checked, paths.len());
+    assert!(!report.sampled.contains(&paths[1]));
+    assert!(report.failures.is_empty());
+
+    let report = verifier.replay_archive::<MockPlonkCircuit>(&paths, &sampled[0]);
+    assert!(report.sampled.contains(&paths[1]));
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].file, paths[1]);
+    assert_eq!(report.failures[0].layer, IntegrityLayer::Snark);
+
+    // the same seed selects the same subset
+    let again = verifier.replay_archive::<MockPlonkCircuit>(&paths, &sampled[0]);
+    assert_eq!(report.sampled, again.sampled);
+}
+
+// Changing a public input of a valid proof makes it fail verification.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_instance_override() {
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    init();
+
+    let (prover, circuit, proof) = mock_plonk_proof(8);
+    let mut verifier = Verifier::new(prover.params.clone(), prover.params.clone(), None);
+    verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&proof)
+        .unwrap();
+
+    let value = circuit.instances()[0][0] + Fr::one();
+    let overridden = prover
+        .create_proof_with_instance_override::<MockPlonkCircuit>(&proof, &[(0, value)])
+        .unwrap();
+    assert_eq!(overridden.snark.instances, vec![vec![value]]);
+    assert_eq!(overridden.snark.proof, proof.snark.proof);
+    assert!(verifier
+        .verify_target_circuit_proof::<MockPlonkCircuit>(&overridden)
+        .is_err());
+
+    assert!(prover
+        .create_proof_with_instance_override::<MockPlonkCircuit>(&proof, &[(1, value)])
+        .is_err());
+}
+
+// Diverging public inputs of two redundant proofs are reported with the offending position.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_redundancy_mismatch() {
+    use zkevm::prover::RedundancyMismatch;
+
+    init();
+
+    let (mut prover, circuit, _) = mock_plonk_proof(8);
+    let mut prove_redundant = |perturbed: Option<StandardPlonk>| {
+        prover.prove_redundant_with::<MockPlonkCircuit>(
+            [[0u8; 16], [1u8; 16]],
+            |prover, run, rng| {
+                // test hook: perturb the public data of the second run
+                let circuit = match perturbed {
+                    Some(perturbed) if run == 1 => perturbed,
+                    _ => circuit,
+                };
+                Ok(prove_mock_plonk(prover, circuit, rng))
+            },
+        )
+    };
+    let result = prove_redundant(None).unwrap();
+    assert_eq!(result.run_ids.len(), 2);
+
+    let perturbed = StandardPlonk::rand(&mut XorShiftRng::from_seed([1u8; 16]));
+    let err = prove_redundant(Some(perturbed)).unwrap_err();
+    let err = err.downcast::<RedundancyMismatch>().unwrap();
+    assert_eq!(err.field, "instances[0][0]");
+}
+
+// keygen is reproducible, and an attestation only holds for the params and the circuit it was
+// made with.
+#[test]
+fn test_vk_attestation() {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use halo2_proofs::SerdeFormat;
+    use mock_plonk::MockPlonkCircuitV2;
+    use zkevm::attest::{
+        generate_vk, generate_vk_attestation, verify_vk_attestation, AttestationMismatch,
+    };
+
+    init();
+
+    let dir = std::env::temp_dir().join("test_vk_attestation");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let write_params = |name: &str, k: u32, seed: u8| {
+        let path = dir.join(name);
+        let params = ParamsKZG::<Bn256>::setup(k, XorShiftRng::from_seed([seed; 16]));
+        let mut file = std::fs::File::create(&path).unwrap();
+        params
+            .write_custom(&mut file, SerdeFormat::RawBytesUnchecked)
+            .unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let params_path = write_params("params8", 8, 0);
+    let params_path = params_path.as_str();
+    let mismatch = |err: anyhow::Error| err.downcast::<AttestationMismatch>().unwrap().field;
+
+    let attestation = generate_vk_attestation::<MockPlonkCircuit>(params_path).unwrap();
+    assert_eq!(attestation.circuit_name, "standard plonk");
+    assert_eq!(attestation.circuit_parameters.degree, 8);
+    assert_eq!(
+        attestation,
+        generate_vk_attestation::<MockPlonkCircuit>(params_path).unwrap()
+    );
+
+    let local_vk = generate_vk::<MockPlonkCircuit>(params_path).unwrap();
+    assert_eq!(
+        local_vk,
+        generate_vk::<MockPlonkCircuit>(params_path).unwrap()
+    );
+    // the parallel parts of keygen do not depend on the number of threads
+    let single_thread = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    assert_eq!(
+        local_vk,
+        single_thread
+            .install(|| generate_vk::<MockPlonkCircuit>(params_path))
+            .unwrap()
+    );
+    verify_vk_attestation::<MockPlonkCircuit>(&attestation, params_path, &local_vk).unwrap();
+
+    // another version of the circuit under the same name gives another vk
+    let v2_vk = generate_vk::<MockPlonkCircuitV2>(params_path).unwrap();
+    assert_ne!(v2_vk, local_vk);
+    let err =
+        verify_vk_attestation::<MockPlonkCircuitV2>(&attestation, params_path, &v2_vk).unwrap_err();
+    assert_eq!(mismatch(err), "vk_sha256");
+
+    // another degree gives another vk
+    let params9_path = write_params("params9", 9, 0);
+    let vk9 = generate_vk::<MockPlonkCircuit>(&params9_path).unwrap();
+    assert_ne!(vk9, local_vk);
+    let err =
+        verify_vk_attestation::<MockPlonkCircuit>(&attestation, &params9_path, &vk9).unwrap_err();
+    assert_eq!(mismatch(err), "degree");
+
+    // other params of the same degree give another vk
+    let other_params_path = write_params("other_params8", 8, 1);
+    let other_vk = generate_vk::<MockPlonkCircuit>(&other_params_path).unwrap();
+    assert_ne!(other_vk, local_vk);
+    let err =
+        verify_vk_attestation::<MockPlonkCircuit>(&attestation, &other_params_path, &other_vk)
+            .unwrap_err();
+    assert_eq!(mismatch(err), "params_sha256");
+
+    let mut changed = attestation.clone();
+    changed.circuit_name = "another circuit".to_string();
+    let err =
+        verify_vk_attestation::<MockPlonkCircuit>(&changed, params_path, &local_vk).unwrap_err();
+    assert_eq!(mismatch(err), "circuit_name");
+
+    let mut changed = attestation.clone();
+    changed.circuit_parameters.max_txs += 1;
+    let err =
+        verify_vk_attestation::<MockPlonkCircuit>(&changed, params_path, &local_vk).unwrap_err();
+    assert_eq!(mismatch(err), "max_txs");
+
+    let err = verify_vk_attestation::<MockPlonkCircuit>(&attestation, params_path, &local_vk[1..])
+        .unwrap_err();
+    assert_eq!(mismatch(err), "vk_sha256");
+}
+
+// Only the options which change the circuits or the proofs are part of the config hash.
+#[test]
+fn test_config_hash() {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+
+    let seed = [0u8; 16];
+    let params = ParamsKZG::<Bn256>::setup(8, XorShiftRng::from_seed(seed));
+    let prover = Prover::from_params_and_seed(params.clone(), params.clone(), seed);
+    assert_eq!(prover.options.pi_mode, "pi_hash");
+    assert_eq!(prover.options.transcript, "poseidon");
+    assert_eq!(prover.options.multi_open, "shplonk");
+
+    let mut operational = Prover::from_params_and_seed(params.clone(), params.clone(), seed);
+    operational.options.parallel_witness = !operational.options.parallel_witness;
+    operational.options.opt_mem = !operational.options.opt_mem;
+    operational.options.allow_config_mismatch = !operational.options.allow_config_mismatch;
+    assert_eq!(
+        prover.options.config_hash(),
+        operational.options.config_hash()
+    );
+
+    // target circuit params of another degree give other circuits
+    let params9 = ParamsKZG::<Bn256>::setup(9, XorShiftRng::from_seed(seed));
+    let semantic = Prover::from_params_and_seed(params9, params, seed);
+    assert_ne!(prover.options.config_hash(), semantic.options.config_hash());
+}
+
+// Proofs generated with another config are not aggregated, unless explicitly allowed.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_aggregation_config_mismatch() {
+    use zkevm::prover::{check_config_hashes, ConfigHashMismatch};
+
+    std::env::set_var("VERIFY_CONFIG", "./configs/example_evm_accumulator.config");
+
+    init();
+
+    let k = 8;
+    let (mut prover, circuit, proof) = mock_plonk_proof_with_agg(k, 21);
+    let mut rng = prover.rng.clone();
+    // the same circuit over a larger domain: another vk
+    let params_drifted = {
+        let mut params = prover.agg_params.clone();
+        params.downsize(k + 1);
+        params
+    };
+    let mut drifted =
+        Prover::from_params_and_seed(params_drifted, prover.agg_params.clone(), [0u8; 16]);
+
+    let mut proofs = [proof, prove_mock_plonk(&mut drifted, circuit, &mut rng)];
+    assert_ne!(proofs[0].vk, proofs[1].vk);
+    assert_ne!(proofs[0].config_hash, proofs[1].config_hash);
+
+    let err = prover
+        .create_agg_proof_by_inner_proofs(&proofs, &mut rng)
+        .unwrap_err();
+    assert_eq!(err.downcast_ref::<ConfigHashMismatch>().unwrap().index, 1);
+
+    // the override aggregates them anyway
+    prover.options.allow_config_mismatch = true;
+    prover
+        .create_agg_proof_by_inner_proofs(&proofs, &mut rng)
+        .unwrap();
+
+    // proofs from before config hashes are accepted unchecked
+    let expected = prover.options.config_hash_hex();
+    proofs[1].config_hash.clear();
+    check_config_hashes(&proofs, &expected).unwrap();
+}
+
+// The bytes streamed to the sink are the proof of the non-streaming path.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_streaming_proof_upload() {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use zkevm::io::StreamingProofUploader;
+
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    init();
+
+    let seed = [1u8; 16];
+    let (mut prover, circuit, _) = mock_plonk_proof(8);
+    let proof = prove_mock_plonk(&mut prover, circuit, &mut XorShiftRng::from_seed(seed));
+
+    let sink = SharedSink::default();
+    prover
+        .create_target_circuit_proof_streaming::<MockPlonkCircuit>(
+            circuit,
+            &mut XorShiftRng::from_seed(seed),
+            StreamingProofUploader::new(sink.clone()),
+        )
+        .unwrap();
+    assert_eq!(*sink.0.lock().unwrap(), proof.snark.proof);
+}
+
+// Failures with the same root cause share a fingerprint, whatever the proof contents.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_verify_failure_fingerprints() {
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use zkevm::verifier::VerifyError;
+
+    init();
+
+    let (prover, circuit, proof) = mock_plonk_proof(8);
+    let mut verifier = Verifier::new(prover.params.clone(), prover.params.clone(), None);
+    verifier.set_recent_failures_capacity(2);
+    let builtin_vk = verifier.target_circuit_vk_fingerprint::<MockPlonkCircuit>();
+
+    let mut verify_err = |proof| {
+        verifier
+            .verify_target_circuit_proof::<MockPlonkCircuit>(&proof)
+            .unwrap_err()
+            .downcast::<VerifyError>()
+            .unwrap()
+    };
+    let wrong_instance = |delta: u64| {
+        let value = circuit.instances()[0][0] + Fr::from(delta);
+        prover
+            .create_proof_with_instance_override::<MockPlonkCircuit>(&proof, &[(0, value)])
+            .unwrap()
+    };
+    let first = verify_err(wrong_instance(1));
+    let second = verify_err(wrong_instance(2));
+    assert!(matches!(first, VerifyError::Snark { .. }));
+    assert_eq!(first.fingerprint(), second.fingerprint());
+    assert_eq!(first.vk_fingerprint(), Some(builtin_vk.as_str()));
+
+    // the vk claimed by the proof is not the one it is verified against
+    let mut foreign_vk = wrong_instance(1);
+    foreign_vk.vk[0] ^= 1;
+    let third = verify_err(foreign_vk);
+    assert_eq!(third.fingerprint(), first.fingerprint());
+
+    let mut extra_column = prover
+        .create_proof_with_instance_override::<MockPlonkCircuit>(&proof, &[])
+        .unwrap();
+    extra_column.snark.instances.push(vec![Fr::one()]);
+    let shape = verify_err(extra_column);
+    assert!(matches!(
+        shape,
+        VerifyError::InstanceShape {
+            expected: 1,
+            got: 2,
+            ..
+        }
+    ));
+    assert_ne!(first.fingerprint(), shape.fingerprint());
+
+    let recent = verifier.recent_failures();
+    assert_eq!(recent.len(), 2);
+    assert_eq!(recent[0].error, second);
+    assert_eq!(recent[1].error, shape);
+    let counts = verifier.failure_counts();
+    assert_eq!(counts[&first.fingerprint()], 3);
+    assert_eq!(counts[&shape.fingerprint()], 1);
+}
+
+// A prover generic over the proof system routes setup, proving and verification to it, and
+// keeps a single proving key per circuit.
+#[test]
+fn test_mock_proof_system() {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::Rng;
+    use std::sync::Mutex;
+    use zkevm::circuit::TargetCircuit;
+    use zkevm::prover::ProofSystem;
+
+    /// Always succeeds, and logs the calls it gets. Its keys and proofs are strings, and it has
+    /// no params.
+    #[derive(Debug, Default)]
+    struct MockProofSystem {
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl MockProofSystem {
+        fn log(&self, call: &str, circuit: String) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", call, circuit));
+        }
+    }
+
+    impl ProofSystem for MockProofSystem {
+        type Params = ();
+        type ProvingKey = String;
+        type VerifyingKey = String;
+        type Proof = String;
+
+        fn params<'a>(&'a self, _kzg_params: &'a ParamsKZG<Bn256>) -> &'a () {
+            &()
+        }
+
+        fn setup<C: TargetCircuit>(
+            &self,
+            _params: &(),
+            _circuit: &C::Inner,
+        ) -> anyhow::Result<String> {
+            self.log("setup", C::name());
+            Ok(format!("key of {}", C::name()))
+        }
+
+        fn verifying_key(pk: &String) -> &String {
+            pk
+        }
+
+        fn prove<C: TargetCircuit>(
+            &self,
+            _params: &(),
+            pk: &String,
+            _circuit: C::Inner,
+            _rng: &mut (impl Rng + Send),
+        ) -> anyhow::Result<String> {
+            self.log("prove", C::name());
+            Ok(format!("proof with {}", pk))
+        }
+
+        fn verify<C: TargetCircuit>(
+            &self,
+            _params: &(),
+            vk: &String,
+            proof: &String,
+        ) -> anyhow::Result<bool> {
+            self.log("verify", C::name());
+            Ok(*proof == format!("proof with {}", vk))
+        }
+    }
+
+    let seed = [0u8; 16];
+    let mut rng = XorShiftRng::from_seed(seed);
+    let params = ParamsKZG::<Bn256>::setup(8, &mut rng);
+    let circuit = StandardPlonk::rand(&mut rng);
+    let mut prover = Prover::with_system(
+        params.clone(),
+        params.clone(),
+        XorShiftRng::from_seed(seed),
+        MockProofSystem::default(),
+    );
+
+    let proof = prover
+        .prove_with_system::<MockPlonkCircuit>(circuit, &mut rng)
+        .unwrap();
+    assert_eq!(proof, "proof with key of standard plonk");
+    assert!(prover
+        .verify_with_system::<MockPlonkCircuit>(&proof)
+        .unwrap());
+    assert_eq!(
+        *prover.system.calls.lock().unwrap(),
+        [
+            "setup standard plonk",
+            "prove standard plonk",
+            "verify standard plonk"
+        ]
+    );
+    assert_eq!(prover.target_circuit_pks.len(), 1);
+    assert_eq!(
+        prover.target_circuit_pks["standard plonk"],
+        "key of standard plonk"
+    );
+
+    // the default system proves with the key of the other prove paths
+    let mut prover = Prover::from_params_and_seed(params.clone(), params, seed);
+    let snark = prover
+        .prove_with_system::<MockPlonkCircuit>(circuit, &mut rng)
+        .unwrap();
+    assert!(prover
+        .verify_with_system::<MockPlonkCircuit>(&snark)
+        .unwrap());
+    let proof = prover
+        .create_target_circuit_proof_from_circuit::<MockPlonkCircuit>(
+            circuit,
+            circuit.instances(),
+            &mut rng,
+            0,
+            0,
+        )
+        .unwrap();
+    assert_eq!(prover.target_circuit_pks.len(), 1);
+    assert_eq!(
+        proof.vk,
+        serialize_vk(prover.target_circuit_pks["standard plonk"].get_vk())
+    );
+}
+
+// The report of the latest mock prove is kept by the prover.
+#[test]
+fn test_last_mock_prove_report() {
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use zkevm::prover::MockProveError;
+
+    init();
+
+    let seed = [0u8; 16];
+    let mut rng = XorShiftRng::from_seed(seed);
+    let params = ParamsKZG::<Bn256>::setup(8, &mut rng);
+    let circuit = StandardPlonk::rand(&mut rng);
+    let mut prover = Prover::from_params_and_seed(params.clone(), params, seed);
+    assert!(prover.last_mock_prove_report().is_none());
+
+    prover
+        .mock_prove_circuit::<MockPlonkCircuit>(&circuit, circuit.instances())
+        .unwrap();
+    assert!(prover
+        .last_mock_prove_report()
+        .unwrap()
+        .violations
+        .is_empty());
+
+    // a wrong public input breaks the gate at the first row
+    let wrong_instances = vec![vec![circuit.instances()[0][0] + Fr::one()]];
+    let err = prover
+        .mock_prove_circuit::<MockPlonkCircuit>(&circuit, wrong_instances)
+        .unwrap_err();
+    let err = err.downcast_ref::<MockProveError>().unwrap();
+    assert_eq!(err.total_violations, 1);
+    assert_eq!(err.first_violation.kind, "ConstraintNotSatisfied");
+
+    let report = prover.last_mock_prove_report().unwrap();
+    assert_eq!(report.circuit, "standard plonk");
+    assert_eq!(report.violations.len(), 1);
+    assert_eq!(report.violations[0], err.first_violation);
+}
+
+// The manifest is written next to the proof, and is validated when the proof is restored.
+#[cfg(feature = "prove_verify")]
+#[test]
+fn test_proof_manifest() {
+    use zkevm::io::{manifest_path, read_proof_manifest, write_proof_manifest, ManifestMismatch};
+    use zkevm::prover::TargetCircuitProof;
+
+    init();
+
+    let (prover, _, proof) = mock_plonk_proof(8);
+
+    let dir = std::env::temp_dir().join("test_proof_manifest");
+    std::fs::create_dir_all(&dir).unwrap();
+    let proof_path = dir.join("proof.json");
+    proof.dump_to_file(proof_path.to_str().unwrap()).unwrap();
+    write_proof_manifest(
+        &proof.name,
+        &proof.block_numbers,
+        &proof.vk,
+        prover.params_sha256(),
+        &proof_path,
+    )
+    .unwrap();
+
+    let manifest_file = manifest_path(&proof_path);
+    assert_eq!(manifest_file, dir.join("proof.json.manifest.json"));
+    let manifest = read_proof_manifest(&proof_path).unwrap().unwrap();
+    assert_eq!(manifest.circuit, "standard plonk");
+    assert_eq!(
+        manifest.params_sha256,
+        zkevm::io::params_sha256(&prover.params)
+    );
+    assert_eq!(
+        manifest.vk_fingerprint,
+        zkevm::io::vk_fingerprint(&proof.vk)
+    );
+
+    let (restored, restored_manifest) =
+        TargetCircuitProof::restore_with_manifest(proof_path.to_str().unwrap())
+            .unwrap()
+            .unwrap();
+    assert_eq!(restored.name, proof.name);
+    assert_eq!(restored_manifest, Some(manifest.clone()));
+
+    let mut tampered = manifest;
+    tampered.circuit = "super".to_string();
+    std::fs::write(&manifest_file, serde_json::to_vec(&tampered).unwrap()).unwrap();
+    let err = TargetCircuitProof::restore_with_manifest(proof_path.to_str().unwrap()).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ManifestMismatch>().unwrap().field,
+        "circuit"
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// A circuit which does not describe itself gets the default description.
+#[test]
+fn test_default_describe() {
+    use zkevm::circuit::TargetCircuit;
+
+    let description = MockPlonkCircuit::describe();
+    assert_eq!(description.name, MockPlonkCircuit::name());
+    assert!(description.sub_circuits.is_empty());
+    assert_eq!(description.public_inputs.len(), 1);
+    assert_eq!(
+        description.public_inputs[0].len,
+        MockPlonkCircuit::public_input_len()
+    );
+}
+
+// Set UPDATE_GOLDEN=true to regenerate the golden summary after an intended circuit change.
+#[test]
+fn test_mock_constraint_summary_golden() {
+    init();
+    assert_constraint_summary_golden::<MockPlonkCircuit>(
+        "./tests/golden/standard_plonk_constraints.json",
+    );
+}
@@ -1,13 +1,13 @@
 use chrono::Utc;
 use git_version::git_version;
-use glob::glob;
 use std::fs;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Once;
 use types::eth::BlockTrace;
-use zkevm::utils::get_block_trace_from_file;
+use zkevm::circuit::{constraint_summary, write_constraint_summary, TargetCircuit};
 use zkevm::utils::read_env_var;
+use zkevm::utils::{get_block_trace_from_file, load_batch_traces_bounded};
 
 pub const GIT_VERSION: &str = git_version!();
 pub const PARAMS_DIR: &str = "./test_params";
@@ -37,31 +37,9 @@ pub fn create_output_dir() -> String {
 }
 
 pub fn load_batch_traces(batch_dir: &str) -> (Vec<String>, Vec<types::eth::BlockTrace>) {
-    let file_names: Vec<String> = glob(&format!("{batch_dir}/**/*.json"))
-        .unwrap()
-        .map(|p| p.unwrap().to_str().unwrap().to_string())
-        .collect();
-    log::info!("test batch with {:?}", file_names);
-    let mut names_and_traces = file_names
-        .into_iter()
-        .map(|trace_path| {
-            let trace: BlockTrace = get_block_trace_from_file(trace_path.clone());
-            (
-                trace_path,
-                trace.clone(),
-                trace.header.number.unwrap().as_u64(),
-            )
-        })
-        .collect::<Vec<_>>();
-    names_and_traces.sort_by(|a, b| a.2.cmp(&b.2));
-    log::info!(
-        "sorted: {:?}",
-        names_and_traces
-            .iter()
-            .map(|(f, _, _)| f.clone())
-            .collect::<Vec<String>>()
-    );
-    names_and_traces.into_iter().map(|(f, t, _)| (f, t)).unzip()
+    let max_batch_size_mb = read_env_var("MAX_BATCH_SIZE_MB", usize::MAX >> 20);
+    load_batch_traces_bounded(batch_dir, max_batch_size_mb.saturating_mul(1 << 20))
+        .unwrap_or_else(|e| panic!("failed to load batch {batch_dir}: {e}"))
 }
 
 pub fn parse_trace_path_from_mode(mode: &str) -> &'static str {
@@ -101,3 +79,65 @@ pub fn load_block_traces_for_test() -> (Vec<String>, Vec<BlockTrace>) {
     let traces: Vec<_> = paths.iter().map(get_block_trace_from_file).collect();
     (paths, traces)
 }
+
+/// Paths and values where two json documents differ, one `path: golden -> current` per line.
+pub fn json_diff(
+    path: &str,
+    golden: &serde_json::Value,
+    current: &serde_json::Value,
+) -> Vec<String> {
+    use serde_json::Value;
+    match (golden, current) {
+        (Value::Object(golden), Value::Object(current)) => {
+            let keys: std::collections::BTreeSet<_> = golden.keys().chain(current.keys()).collect();
+            keys.into_iter()
+                .flat_map(|key| {
+                    json_diff(
+                        &format!("{}.{}", path, key),
+                        golden.get(key).unwrap_or(&Value::Null),
+                        current.get(key).unwrap_or(&Value::Null),
+                    )
+                })
+                .collect()
+        }
+        (Value::Array(golden), Value::Array(current)) => (0..golden.len().max(current.len()))
+            .flat_map(|i| {
+                json_diff(
+                    &format!("{}[{}]", path, i),
+                    golden.get(i).unwrap_or(&Value::Null),
+                    current.get(i).unwrap_or(&Value::Null),
+                )
+            })
+            .collect(),
+        _ if golden == current => vec![],
+        _ => vec![format!("{}: {} -> {}", path, golden, current)],
+    }
+}
+
+/// Compare the constraint summary of `C` with the committed one at `golden_path`.
+///
+/// With UPDATE_GOLDEN=true the file is written instead. A missing file fails the test: the
+/// golden summary is only ever written on request, to be reviewed and committed.
+pub fn assert_constraint_summary_golden<C: TargetCircuit>(golden_path: &str) {
+    if read_env_var("UPDATE_GOLDEN", false) {
+        write_constraint_summary::<C>(golden_path).unwrap();
+        log::info!("wrote {}, review and commit it", golden_path);
+        return;
+    }
+
+    let golden = fs::read(golden_path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read the golden summary {}: {}, set UPDATE_GOLDEN=true to write it",
+            golden_path, e
+        )
+    });
+    let golden: serde_json::Value = serde_json::from_slice(&golden).unwrap();
+    let current = serde_json::to_value(constraint_summary::<C>()).unwrap();
+    let diff = json_diff("", &golden, &current);
+    assert!(
+        diff.is_empty(),
+        "constraint system of {} changed, set UPDATE_GOLDEN=true if intended:\n{}",
+        C::name(),
+        diff.join("\n")
+    );
+}